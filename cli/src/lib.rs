@@ -0,0 +1,298 @@
+//! The `arma-bench` command line client, for benchmarks that don't need any Rust.
+
+mod output;
+
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use arma_bench::{
+    Baseline, Client, CompareRequest, Group, Options, Report, Request, ServerConfig, Suite,
+    DEFAULT_PORT, DEFAULT_TOLERANCE,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+
+pub use output::{compare, execute, report};
+
+/// Benchmark SQF on an arma-bench server.
+#[derive(Debug, Parser)]
+#[command(name = "arma-bench", version)]
+pub struct Cli {
+    /// The server to connect to.
+    #[arg(long, global = true, default_value = "localhost")]
+    pub host: String,
+    /// The port the server listens on.
+    #[arg(long, global = true, default_value_t = DEFAULT_PORT)]
+    pub port: u16,
+    /// The Steam branch of the Arma server, such as `profiling`.
+    #[arg(long, global = true)]
+    pub branch: Option<String>,
+    /// The password of the branch, if it has one.
+    #[arg(long, global = true)]
+    pub branch_password: Option<String>,
+    /// The Arma server binary to run, such as `arma3serverprofiling_x64`.
+    #[arg(long, global = true)]
+    pub binary: Option<String>,
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+    /// How many times to benchmark each script.
+    #[arg(long, global = true)]
+    pub repetitions: Option<u32>,
+    /// How many times to start Arma.
+    #[arg(long, global = true)]
+    pub boots: Option<u32>,
+    /// How many seconds each start of Arma may take, the server decides if not set.
+    #[arg(long, global = true)]
+    pub timeout: Option<u64>,
+    /// A file of saved results to compare against, failing scripts that got slower.
+    #[arg(long, global = true)]
+    pub baseline: Option<PathBuf>,
+    /// Save the results to the baseline file, replacing what was saved for the same scripts.
+    #[arg(long, global = true, requires = "baseline")]
+    pub save_baseline: bool,
+    /// How much slower than the baseline a script may be, such as 0.05 for 5%.
+    #[arg(long, global = true, default_value_t = DEFAULT_TOLERANCE)]
+    pub tolerance: f64,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Benchmark a script and show what it returned.
+    Execute {
+        /// An .sqf file, or - to read it from stdin.
+        #[arg(default_value = "-")]
+        script: PathBuf,
+    },
+    /// Benchmark scripts against the first.
+    Compare {
+        /// .sqf or .sqfc files, one of them can be - to read it from stdin.
+        #[arg(num_args = 2.., required = true)]
+        scripts: Vec<PathBuf>,
+    },
+    /// Run every benchmark in a suite manifest, failing if any of them fail.
+    Suite {
+        /// A .toml suite manifest.
+        manifest: PathBuf,
+    },
+}
+
+/// How results are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Aligned columns, for reading.
+    Table,
+    /// For other programs.
+    Json,
+    /// One row per script.
+    Csv,
+    /// A GitHub flavored table, for pull request comments.
+    Markdown,
+    /// `JUnit` XML, for CI.
+    Junit,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read {name}: {source}")]
+    Read {
+        name: String,
+        source: std::io::Error,
+    },
+    #[error("{0} is not UTF-8")]
+    NotUtf8(String),
+    #[error("{0} is compiled, only compare runs .sqfc")]
+    Compiled(String),
+    #[error("only one script can be read from stdin")]
+    StdinTwice,
+    #[error(transparent)]
+    Client(#[from] arma_bench::Error),
+    #[error("failed to write output: {0}")]
+    Output(#[from] std::io::Error),
+    #[error("failed to write JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0} of the benchmarked scripts failed")]
+    Failed(usize),
+}
+
+impl Cli {
+    /// The defaults of [`ServerConfig`], with any that were passed.
+    #[must_use]
+    pub fn server_config(&self) -> ServerConfig {
+        let default = ServerConfig::default();
+        ServerConfig {
+            binary: self.binary.clone().unwrap_or(default.binary),
+            branch: self.branch.clone().unwrap_or(default.branch),
+            branch_password: self
+                .branch_password
+                .clone()
+                .unwrap_or(default.branch_password),
+        }
+    }
+
+    #[must_use]
+    pub fn options(&self) -> Options {
+        Options {
+            timeout: self.timeout,
+            repetitions: self.repetitions,
+            boots: self.boots,
+            ..Default::default()
+        }
+    }
+}
+
+/// A script read from a file or stdin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    /// The path as it was given, or `stdin`.
+    pub name: String,
+    pub sqfc: bool,
+    pub content: Vec<u8>,
+}
+
+impl Script {
+    /// Read the script at `path`, where `-` reads `stdin`.
+    ///
+    /// # Errors
+    /// Returns an error if the script can not be read.
+    pub fn read(path: &Path, mut stdin: impl Read) -> Result<Self, Error> {
+        if path == Path::new("-") {
+            let mut content = Vec::new();
+            stdin
+                .read_to_end(&mut content)
+                .map_err(|source| Error::Read {
+                    name: "stdin".to_string(),
+                    source,
+                })?;
+            return Ok(Self {
+                name: "stdin".to_string(),
+                sqfc: false,
+                content,
+            });
+        }
+        let name = path.display().to_string();
+        let content = std::fs::read(path).map_err(|source| Error::Read {
+            name: name.clone(),
+            source,
+        })?;
+        Ok(Self {
+            name,
+            sqfc: path.extension().is_some_and(|ext| ext == "sqfc"),
+            content,
+        })
+    }
+
+    /// Read the scripts at `paths`, see [`Script::read`].
+    ///
+    /// # Errors
+    /// Returns an error if a script can not be read, or stdin is given more than once.
+    pub fn read_all(paths: &[PathBuf], mut stdin: impl Read) -> Result<Vec<Self>, Error> {
+        if paths.iter().filter(|path| *path == Path::new("-")).count() > 1 {
+            return Err(Error::StdinTwice);
+        }
+        paths
+            .iter()
+            .map(|path| Self::read(path, &mut stdin))
+            .collect()
+    }
+}
+
+/// Run the command, reading `-` scripts from `stdin` and writing the results to `out`.
+///
+/// # Errors
+/// Returns an error if a script can not be read, the benchmark fails, the results can not be written,
+/// or a script fails its assertions, thresholds or baseline.
+pub fn run(cli: &Cli, stdin: impl Read, out: impl Write) -> Result<(), Error> {
+    let branch = Some(cli.server_config().branch);
+    match &cli.command {
+        Command::Execute { script } => {
+            let script = Script::read(script, stdin)?;
+            if script.sqfc {
+                return Err(Error::Compiled(script.name));
+            }
+            let content = String::from_utf8(script.content)
+                .map_err(|_| Error::NotUtf8(script.name.clone()))?;
+            let result = Client::connect_with_port(&cli.host, cli.port, &cli.server_config())?
+                .submit_with_options(Request::Execute(content), cli.options())?
+                .wait()?
+                .into_execute()?;
+            let report = Report {
+                groups: vec![Group {
+                    branch,
+                    ..Group::execute(&script.name, &result)
+                }],
+            };
+            if cli.baseline.is_none() {
+                execute(cli.format, &script.name, &result, out)?;
+                return passed(&report);
+            }
+            finish(cli, report, out)
+        }
+        Command::Compare { scripts } => {
+            let scripts = Script::read_all(scripts, stdin)?;
+            let requests = (0..)
+                .zip(&scripts)
+                .map(|(id, script)| CompareRequest {
+                    id,
+                    sqfc: script.sqfc,
+                    content: script.content.clone(),
+                })
+                .collect();
+            let results = Client::connect_with_port(&cli.host, cli.port, &cli.server_config())?
+                .submit_with_options(Request::Compare(requests), cli.options())?
+                .wait()?
+                .into_compare()?;
+            let names = scripts
+                .into_iter()
+                .map(|script| script.name)
+                .collect::<Vec<_>>();
+            let group = Group::compare("compare", &names, &results, 0)
+                .ok_or(arma_bench::Error::UnexpectedResponse)?;
+            let report = Report {
+                groups: vec![Group { branch, ..group }],
+            };
+            if cli.baseline.is_none() {
+                compare(cli.format, &names, &results, out)?;
+                return passed(&report);
+            }
+            finish(cli, report, out)
+        }
+        Command::Suite { manifest } => {
+            let results = Suite::load(manifest)?.run(
+                &cli.host,
+                cli.port,
+                &cli.server_config(),
+                &cli.options(),
+            )?;
+            finish(cli, results, out)
+        }
+    }
+}
+
+/// Compare `results` against the baseline and write them,
+/// failing if any script failed.
+///
+/// The baseline is only saved when every script passed, so a bad run never replaces a good one.
+fn finish(cli: &Cli, mut results: Report, out: impl Write) -> Result<(), Error> {
+    if let Some(path) = &cli.baseline {
+        let mut baseline = Baseline::load(path)?;
+        baseline.compare(&mut results, cli.tolerance);
+        if cli.save_baseline && !results.failed() {
+            baseline.record(&results);
+            baseline.save(path)?;
+        }
+    }
+    report(cli.format, &results, out)?;
+    passed(&results)
+}
+
+/// Fail if any script in `results` failed, such as with script errors or a wrong result.
+fn passed(results: &Report) -> Result<(), Error> {
+    let failed = results.failures();
+    if failed > 0 {
+        return Err(Error::Failed(failed));
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use std::process::ExitCode;
+
+use arma_bench_cli::{Cli, Error};
+use clap::Parser;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match arma_bench_cli::run(&cli, std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            if let Error::Client(arma_bench::Error::CompileFailed { errors, .. }) = &e {
+                // Named by the file they are in, which may be the setup rather than the script.
+                for error in errors {
+                    eprintln!("{error}");
+                }
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
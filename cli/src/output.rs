@@ -0,0 +1,273 @@
+use std::{ffi::OsStr, io::Write, path::Path};
+
+use arma_bench::{
+    to_sqf, CompareResult, Comparison, ExecuteResult, Group, Report, ReportFormat, ScriptError,
+    Statistics,
+};
+use arma_rs::Value;
+use serde::Serialize;
+
+use crate::{Error, Format};
+
+#[derive(Serialize)]
+struct ExecuteOutput<'a> {
+    script: &'a str,
+    result: &'a ExecuteResult,
+    statistics: Statistics,
+}
+
+#[derive(Serialize)]
+struct CompareOutput<'a> {
+    /// By ID.
+    scripts: &'a [String],
+    results: &'a [CompareResult],
+    comparison: &'a Comparison,
+}
+
+/// Write the result of executing `script`.
+///
+/// # Errors
+/// Returns an error if `out` can not be written to.
+pub fn execute(
+    format: Format,
+    script: &str,
+    result: &ExecuteResult,
+    mut out: impl Write,
+) -> Result<(), Error> {
+    let statistics = result.statistics();
+    let group = || Group::execute(script, result);
+    match format {
+        Format::Csv => single(group(), ReportFormat::Csv, out)?,
+        Format::Markdown => single(group(), ReportFormat::Markdown, out)?,
+        Format::Junit => single(group(), ReportFormat::Junit, out)?,
+        Format::Json => {
+            serde_json::to_writer_pretty(
+                &mut out,
+                &ExecuteOutput {
+                    script,
+                    result,
+                    statistics,
+                },
+            )?;
+            writeln!(out)?;
+        }
+        Format::Table => {
+            let ms = |time: f64| format!("{time:.6} ms");
+            let rows = [
+                ["Script".to_string(), script.to_string()],
+                ["Result".to_string(), value(&result.ret)],
+                ["Iterations".to_string(), result.iter.to_string()],
+                ["Samples".to_string(), statistics.samples.len().to_string()],
+                ["Mean".to_string(), ms(statistics.mean)],
+                ["Median".to_string(), ms(statistics.median)],
+                ["Std dev".to_string(), ms(statistics.stddev)],
+                ["Min".to_string(), ms(statistics.min)],
+                ["Max".to_string(), ms(statistics.max)],
+            ];
+            write!(out, "{}", table(&rows))?;
+            write_errors(&mut out, script, "bench", &result.errors)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the results of comparing `scripts`, which are named by ID, against the first.
+///
+/// # Errors
+/// Returns an error if the first script has no result, or `out` can not be written to.
+pub fn compare(
+    format: Format,
+    scripts: &[String],
+    results: &[CompareResult],
+    mut out: impl Write,
+) -> Result<(), Error> {
+    let comparison = Comparison::new(results, 0).ok_or(arma_bench::Error::UnexpectedResponse)?;
+    let name = |id: u16| {
+        scripts
+            .get(usize::from(id))
+            .map_or_else(|| id.to_string(), Clone::clone)
+    };
+    let group = || {
+        Group::compare("compare", scripts, results, 0).ok_or(arma_bench::Error::UnexpectedResponse)
+    };
+    match format {
+        Format::Csv => single(group()?, ReportFormat::Csv, out)?,
+        Format::Markdown => single(group()?, ReportFormat::Markdown, out)?,
+        Format::Junit => single(group()?, ReportFormat::Junit, out)?,
+        Format::Json => {
+            serde_json::to_writer_pretty(
+                &mut out,
+                &CompareOutput {
+                    scripts,
+                    results,
+                    comparison: &comparison,
+                },
+            )?;
+            writeln!(out)?;
+        }
+        Format::Table => {
+            let mut rows = vec![[
+                "Rank",
+                "Script",
+                "Mean (ms)",
+                "Interval (ms)",
+                "Speedup",
+                "Verdict",
+                "Result",
+            ]
+            .map(ToString::to_string)];
+            for ranked in &comparison.ranking {
+                let ret = results
+                    .iter()
+                    .find(|result| result.id == ranked.id)
+                    .map(|result| value(&result.ret))
+                    .unwrap_or_default();
+                rows.push([
+                    ranked.rank.to_string(),
+                    name(ranked.id),
+                    format!("{:.6}", ranked.statistics.mean),
+                    format!("{:.6} - {:.6}", ranked.interval.low, ranked.interval.high),
+                    format!("{:.2}x", ranked.speedup),
+                    ranked.verdict.to_string(),
+                    ret,
+                ]);
+            }
+            write!(out, "{}", table(&rows))?;
+            for result in results {
+                write_errors(
+                    &mut out,
+                    &name(result.id),
+                    &result.id.to_string(),
+                    &result.errors,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write a combined report, such as that of a suite.
+///
+/// # Errors
+/// Returns an error if `out` can not be written to.
+pub fn report(format: Format, report: &Report, mut out: impl Write) -> Result<(), Error> {
+    let format = match format {
+        Format::Json => ReportFormat::Json,
+        Format::Csv => ReportFormat::Csv,
+        Format::Markdown => ReportFormat::Markdown,
+        Format::Junit => ReportFormat::Junit,
+        Format::Table => {
+            for (index, group) in report.groups.iter().enumerate() {
+                if index > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}", group.name)?;
+                for failure in &group.failures {
+                    writeln!(out, "{failure}")?;
+                }
+                if group.entries.is_empty() {
+                    continue;
+                }
+                // Only runs compared against a baseline have changes.
+                let changes = group.entries.iter().any(|entry| entry.change.is_some());
+                let mut header = ["Script", "Mean (ms)", "Speedup", "Verdict", "Result"]
+                    .map(ToString::to_string)
+                    .to_vec();
+                if changes {
+                    header.push("Change".to_string());
+                }
+                let mut rows = vec![header];
+                for entry in &group.entries {
+                    let mut row = vec![
+                        entry.name.clone(),
+                        format!("{:.6}", entry.statistics.mean),
+                        entry
+                            .speedup
+                            .map(|speedup| format!("{speedup:.2}x"))
+                            .unwrap_or_default(),
+                        entry
+                            .verdict
+                            .map(|verdict| verdict.to_string())
+                            .unwrap_or_default(),
+                        entry.result.clone(),
+                    ];
+                    if changes {
+                        row.push(
+                            entry
+                                .change
+                                .map(|change| change.to_string())
+                                .unwrap_or_default(),
+                        );
+                    }
+                    rows.push(row);
+                }
+                write!(out, "{}", table(&rows))?;
+                for entry in &group.entries {
+                    for failure in &entry.failures {
+                        writeln!(out, "{}: {failure}", entry.name)?;
+                    }
+                }
+            }
+            return Ok(());
+        }
+    };
+    Ok(report.write(format, out)?)
+}
+
+fn single(group: Group, format: ReportFormat, out: impl Write) -> Result<(), Error> {
+    let report = Report {
+        groups: vec![group],
+    };
+    Ok(report.write(format, out)?)
+}
+
+/// A returned value as SQF would write it.
+fn value(value: &Value) -> String {
+    to_sqf(value).unwrap_or_else(|| value.to_string())
+}
+
+/// Write errors in the script built as `built`, such as `bench` or `0`, against its name `script`.
+/// Errors in other files, such as the setup, keep the name the server gave them.
+fn write_errors(
+    out: &mut impl Write,
+    script: &str,
+    built: &str,
+    errors: &[ScriptError],
+) -> Result<(), Error> {
+    for error in errors {
+        if Path::new(&error.file).file_stem() != Some(OsStr::new(built)) {
+            writeln!(out, "{error}")?;
+            continue;
+        }
+        match error.line {
+            Some(line) => writeln!(out, "{script}:{line}: {}", error.message)?,
+            None => writeln!(out, "{script}: {}", error.message)?,
+        }
+    }
+    Ok(())
+}
+
+/// Left-aligned columns two spaces apart, one row per line.
+fn table(rows: &[impl AsRef<[String]>]) -> String {
+    let mut widths = Vec::new();
+    for row in rows {
+        let row = row.as_ref();
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in rows {
+        let line = row
+            .as_ref()
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
@@ -0,0 +1,123 @@
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use arma_bench::ServerConfig;
+use arma_bench_cli::{Cli, Command, Error, Format, Script};
+use clap::Parser;
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("arma-bench").chain(args.iter().copied()))
+        .expect("Failed to parse")
+}
+
+#[test]
+fn defaults() {
+    let cli = parse(&["execute"]);
+    assert_eq!(cli.host, "localhost");
+    assert_eq!(cli.port, arma_bench::DEFAULT_PORT);
+    assert_eq!(cli.format, Format::Table);
+    assert_eq!(cli.server_config(), ServerConfig::default());
+    assert!(matches!(cli.command, Command::Execute { script } if script == Path::new("-")));
+}
+
+#[test]
+fn server_config() {
+    let cli = parse(&[
+        "compare",
+        "a.sqf",
+        "b.sqfc",
+        "--branch",
+        "profiling",
+        "--binary",
+        "arma3serverprofiling_x64",
+        "--host",
+        "bench.example.com",
+        "--port",
+        "1234",
+    ]);
+    assert_eq!(cli.host, "bench.example.com");
+    assert_eq!(cli.port, 1234);
+    assert_eq!(
+        cli.server_config(),
+        ServerConfig {
+            binary: "arma3serverprofiling_x64".to_string(),
+            branch: "profiling".to_string(),
+            branch_password: String::new(),
+        }
+    );
+}
+
+#[test]
+fn options() {
+    let cli = parse(&[
+        "--repetitions",
+        "10",
+        "--boots",
+        "2",
+        "--format",
+        "json",
+        "execute",
+    ]);
+    assert_eq!(cli.format, Format::Json);
+    let options = cli.options();
+    assert_eq!(options.repetitions, Some(10));
+    assert_eq!(options.boots, Some(2));
+    assert_eq!(options.timeout, None);
+}
+
+#[test]
+fn compare_needs_two_scripts() {
+    assert!(Cli::try_parse_from(["arma-bench", "compare", "a.sqf"]).is_err());
+}
+
+#[test]
+fn read_stdin() {
+    let scripts = Script::read_all(
+        &[PathBuf::from("-"), PathBuf::from("Cargo.toml")],
+        Cursor::new(b"1 + 1"),
+    )
+    .expect("Failed to read");
+    assert_eq!(scripts[0].name, "stdin");
+    assert_eq!(scripts[0].content, b"1 + 1");
+    assert_eq!(scripts[1].name, "Cargo.toml");
+    assert!(!scripts[1].sqfc);
+}
+
+#[test]
+fn stdin_twice() {
+    let res = Script::read_all(&[PathBuf::from("-"), PathBuf::from("-")], Cursor::new(b""));
+    assert!(matches!(res, Err(Error::StdinTwice)));
+}
+
+#[test]
+fn missing_file() {
+    let res = Script::read(&PathBuf::from("missing.sqfc"), Cursor::new(b""));
+    assert!(matches!(res, Err(Error::Read { name, .. }) if name == "missing.sqfc"));
+}
+
+#[test]
+fn baseline() {
+    let cli = parse(&[
+        "suite",
+        "benches.toml",
+        "--baseline",
+        "baseline.json",
+        "--save-baseline",
+        "--tolerance",
+        "0.1",
+    ]);
+    assert_eq!(cli.baseline.as_deref(), Some(Path::new("baseline.json")));
+    assert!(cli.save_baseline);
+    assert!((cli.tolerance - 0.1).abs() < f64::EPSILON);
+    assert!(
+        matches!(cli.command, Command::Suite { manifest } if manifest == Path::new("benches.toml"))
+    );
+}
+
+#[test]
+fn save_baseline_needs_a_file() {
+    let res = Cli::try_parse_from(["arma-bench", "execute", "--save-baseline"]);
+    assert!(res.is_err());
+}
@@ -0,0 +1,172 @@
+use arma_bench::{CompareResult, ExecuteResult, Group, Report, ScriptError, Timing};
+use arma_bench_cli::{compare, execute, report, Format};
+use arma_rs::Value;
+
+fn compare_result(id: u16, samples: &[f64]) -> CompareResult {
+    CompareResult {
+        id,
+        time: samples[0],
+        iter: 10000,
+        ret: Value::Number(3.0),
+        logs: Vec::new(),
+        errors: Vec::new(),
+        samples: samples.to_vec(),
+        mismatches: Vec::new(),
+        timing: Timing::default(),
+    }
+}
+
+fn written(write: impl FnOnce(&mut Vec<u8>)) -> String {
+    let mut out = Vec::new();
+    write(&mut out);
+    String::from_utf8(out).expect("Output is not UTF-8")
+}
+
+#[test]
+fn execute_table() {
+    let result = ExecuteResult {
+        time: 0.001,
+        iter: 10000,
+        ret: Value::Number(3.0),
+        logs: Vec::new(),
+        errors: vec![ScriptError {
+            file: "bench.sqf".to_string(),
+            line: Some(2),
+            message: "Zero divisor".to_string(),
+        }],
+        samples: vec![0.001, 0.003],
+        timing: Timing::default(),
+    };
+    let out = written(|out| {
+        execute(Format::Table, "add.sqf", &result, out).expect("Failed to write");
+    });
+    assert_eq!(
+        out,
+        "\
+Script      add.sqf
+Result      3
+Iterations  10000
+Samples     2
+Mean        0.002000 ms
+Median      0.002000 ms
+Std dev     0.001414 ms
+Min         0.001000 ms
+Max         0.003000 ms
+add.sqf:2: Zero divisor
+"
+    );
+}
+
+#[test]
+fn setup_errors_keep_their_file() {
+    let result = ExecuteResult {
+        time: 0.001,
+        iter: 10000,
+        ret: Value::Number(3.0),
+        logs: Vec::new(),
+        errors: vec![ScriptError {
+            file: "setup.sqf".to_string(),
+            line: Some(4),
+            message: "Undefined variable in expression: _x".to_string(),
+        }],
+        samples: vec![0.001],
+        timing: Timing::default(),
+    };
+    let out = written(|out| {
+        execute(Format::Table, "add.sqf", &result, out).expect("Failed to write");
+    });
+    assert!(out.ends_with("\nsetup.sqf:4: Undefined variable in expression: _x\n"));
+}
+
+#[test]
+fn compare_table() {
+    let results = [
+        compare_result(0, &[0.002, 0.002]),
+        compare_result(1, &[0.001, 0.001]),
+    ];
+    let names = ["slow.sqf".to_string(), "fast.sqfc".to_string()];
+    let out = written(|out| {
+        compare(Format::Table, &names, &results, out).expect("Failed to write");
+    });
+    assert_eq!(
+        out,
+        "\
+Rank  Script     Mean (ms)  Interval (ms)        Speedup  Verdict   Result
+1     fast.sqfc  0.001000   0.001000 - 0.001000  2.00x    faster    3
+2     slow.sqf   0.002000   0.002000 - 0.002000  1.00x    baseline  3
+"
+    );
+}
+
+#[test]
+fn compare_json() {
+    let results = [compare_result(0, &[0.002]), compare_result(1, &[0.001])];
+    let names = ["a.sqf".to_string(), "b.sqf".to_string()];
+    let out = written(|out| {
+        compare(Format::Json, &names, &results, out).expect("Failed to write");
+    });
+    let json: serde_json::Value = serde_json::from_str(&out).expect("Invalid JSON");
+    assert_eq!(json["scripts"][1], "b.sqf");
+    assert_eq!(json["results"][1]["id"], 1);
+    assert_eq!(json["comparison"]["ranking"][0]["id"], 1);
+}
+
+#[test]
+fn missing_baseline() {
+    let results = [compare_result(1, &[0.001])];
+    let res = compare(Format::Table, &[], &results, Vec::new());
+    assert!(res.is_err());
+}
+
+#[test]
+fn compare_markdown() {
+    let results = [
+        compare_result(0, &[0.002, 0.002]),
+        compare_result(1, &[0.001, 0.001]),
+    ];
+    let names = ["a.sqf".to_string(), "b.sqf".to_string()];
+    let out = written(|out| {
+        compare(Format::Markdown, &names, &results, out).expect("Failed to write");
+    });
+    assert!(out.starts_with("### compare\n"));
+    assert!(out.contains("| `b.sqf` | 0.001000 | 0.000000 | 2.00x | faster |"));
+}
+
+#[test]
+fn report_table() {
+    let results = [
+        compare_result(0, &[0.002, 0.002]),
+        compare_result(1, &[0.001, 0.001]),
+    ];
+    let names = ["slow".to_string(), "fast".to_string()];
+    let mut group = Group::compare("count (_size = 10)", &names, &results, 0).expect("No baseline");
+    group.entries[1].failures.push("result differs".to_string());
+    let suite = Report {
+        groups: vec![
+            group.clone(),
+            Group {
+                name: "other".to_string(),
+                ..group
+            },
+        ],
+    };
+    let out = written(|out| {
+        report(Format::Table, &suite, out).expect("Failed to write");
+    });
+    assert_eq!(
+        out,
+        "\
+count (_size = 10)
+Script  Mean (ms)  Speedup  Verdict   Result
+fast    0.001000   2.00x    faster    3
+slow    0.002000   1.00x    baseline  3
+slow: result differs
+
+other
+Script  Mean (ms)  Speedup  Verdict   Result
+fast    0.001000   2.00x    faster    3
+slow    0.002000   1.00x    baseline  3
+slow: result differs
+"
+    );
+}
@@ -1,4 +1,4 @@
-use arma_bench::{Client, CompareRequest, CompareResult, ServerConfig};
+use arma_bench::{Client, CompareRequest, Comparison, Options, Request, ServerConfig};
 
 fn main() {
     let client = Client::connect("localhost", &ServerConfig::default()).expect("Failed to connect");
@@ -16,15 +16,31 @@ fn main() {
             sqfc: false,
         },
     ];
-    let results = client.compare(requests).expect("Failed to compare");
-    for result in results {
-        let CompareResult {
-            id,
-            time,
-            iter,
-            ret,
-        } = result;
-        println!("[{id}] Time: {time} ms, Iterations: {iter}");
-        println!("Result: {ret}");
+    let options = Options {
+        repetitions: Some(10),
+        ..Default::default()
+    };
+    let results = client
+        .submit_with_options(Request::Compare(requests), options)
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+        .into_compare()
+        .expect("Failed to compare");
+    for result in &results {
+        println!("[{}] Result: {}", result.id, result.ret);
+    }
+    let comparison = Comparison::new(&results, 0).expect("Missing baseline");
+    for ranked in comparison.ranking {
+        println!(
+            "#{} [{}] {:.6} ms ({:.6} - {:.6}), {:.2}x, {:?}",
+            ranked.rank,
+            ranked.id,
+            ranked.statistics.mean,
+            ranked.interval.low,
+            ranked.interval.high,
+            ranked.speedup,
+            ranked.verdict,
+        );
     }
 }
@@ -0,0 +1,446 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    sync::mpsc,
+    task::JoinHandle,
+};
+
+use crate::{
+    ClientHello, Command, CompareRequest, CompareResult, Error, ExecuteResult, HistoryQuery,
+    HistoryRecord, MatrixRequest, MatrixResult, Message, Options, Parameters, Progress, Protocol,
+    Reply, Request, RequestId, Response, ServerConfig, ServerHello, DEFAULT_PORT, HEADER_ID,
+    LEGACY_HEADER_ID,
+};
+
+pub struct AsyncClient {
+    shared: Arc<Shared>,
+    protocol: Protocol,
+    next_id: AtomicU64,
+    reader: JoinHandle<()>,
+}
+
+/// State shared between the client, its pending requests, and the task reading responses.
+struct Shared {
+    writer: tokio::sync::Mutex<OwnedWriteHalf>,
+    waiting: Mutex<Waiting>,
+}
+
+#[derive(Default)]
+struct Waiting {
+    requests: HashMap<RequestId, mpsc::UnboundedSender<Response>>,
+    closed: bool,
+}
+
+impl Waiting {
+    fn finish(&mut self, id: Option<RequestId>) {
+        match id {
+            Some(id) => {
+                self.requests.remove(&id);
+            }
+            None => self.requests.clear(),
+        }
+    }
+}
+
+/// Closes the connection for pending requests when the reader stops, even if it is aborted.
+struct Closer(Arc<Shared>);
+
+impl Drop for Closer {
+    fn drop(&mut self) {
+        let mut waiting = self
+            .0
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        waiting.closed = true;
+        waiting.requests.clear();
+        drop(waiting);
+    }
+}
+
+impl AsyncClient {
+    /// Connect to the server with a specific port.
+    ///
+    /// # Errors
+    /// Returns an error if the connection or handshake fails.
+    pub async fn connect_with_port(
+        host: &str,
+        port: u16,
+        config: &ServerConfig,
+    ) -> Result<Self, Error> {
+        let mut stream = TcpStream::connect(format!("{host}:{port}")).await?;
+        // Send the header ID to the server.
+        stream.write_all(HEADER_ID).await?;
+
+        // Expect the server to echo the header ID back to us.
+        let mut buf = [0; 16];
+        stream.read_exact(&mut buf).await?;
+        if buf == *LEGACY_HEADER_ID {
+            return Err(Error::UnsupportedVersion {
+                min_version: 1,
+                max_version: 1,
+            });
+        }
+        if buf != *HEADER_ID {
+            return Err(Error::HandshakeMismatch);
+        }
+
+        // Agree on a protocol version and capabilities.
+        ClientHello::default().write_async(&mut stream).await?;
+        let protocol = ServerHello::from_async_reader(&mut stream)
+            .await?
+            .into_protocol()?;
+
+        // Send the server config to the server.
+        config.write_async(&mut stream).await?;
+
+        // Expect a 1 for wait, disconnect if not.
+        let mut buf = [0; 1];
+        stream.read_exact(&mut buf).await?;
+        if buf[0] != 1 {
+            return Err(Error::InvalidAck(buf[0]));
+        }
+
+        let (reader, writer) = stream.into_split();
+        let shared = Arc::new(Shared {
+            writer: tokio::sync::Mutex::new(writer),
+            waiting: Mutex::default(),
+        });
+        let reader = tokio::spawn(read_responses(Closer(shared.clone()), reader));
+        Ok(Self {
+            shared,
+            protocol,
+            next_id: AtomicU64::new(0),
+            reader,
+        })
+    }
+
+    /// Connect to the server with the default port.
+    ///
+    /// # Errors
+    /// Returns an error if the connection or handshake fails.
+    pub async fn connect(host: &str, config: &ServerConfig) -> Result<Self, Error> {
+        Self::connect_with_port(host, DEFAULT_PORT, config).await
+    }
+
+    /// The protocol version and capabilities negotiated with the server.
+    #[must_use]
+    pub const fn protocol(&self) -> &Protocol {
+        &self.protocol
+    }
+
+    /// Execute a script on the server, returning the benchmark and result.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn execute(&self, content: &str) -> Result<ExecuteResult, Error> {
+        self.execute_with_progress(content, |_| {}).await
+    }
+
+    /// Execute a script on the server, calling `on_progress` as the request moves
+    /// through the queue, then returning the benchmark and result.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn execute_with_progress(
+        &self,
+        content: &str,
+        on_progress: impl FnMut(Progress) + Send,
+    ) -> Result<ExecuteResult, Error> {
+        self.submit(Request::Execute(content.to_string()))
+            .await?
+            .wait_with_progress(on_progress)
+            .await?
+            .into_execute()
+    }
+
+    /// Compare multiple scripts on the server, returning the benchmarks and results.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn compare(
+        &self,
+        requests: Vec<CompareRequest>,
+    ) -> Result<Vec<CompareResult>, Error> {
+        self.compare_with_progress(requests, |_| {}).await
+    }
+
+    /// Compare multiple scripts on the server, calling `on_progress` as the request
+    /// moves through the queue, then returning the benchmarks and results.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn compare_with_progress(
+        &self,
+        requests: Vec<CompareRequest>,
+        on_progress: impl FnMut(Progress) + Send,
+    ) -> Result<Vec<CompareResult>, Error> {
+        self.submit(Request::Compare(requests))
+            .await?
+            .wait_with_progress(on_progress)
+            .await?
+            .into_compare()
+    }
+
+    /// Benchmark a script with each set of parameters, returning the results in the same order.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn matrix(
+        &self,
+        template: &str,
+        parameters: Vec<Parameters>,
+    ) -> Result<Vec<MatrixResult>, Error> {
+        self.matrix_with_progress(template, parameters, |_| {})
+            .await
+    }
+
+    /// Benchmark a script with each set of parameters, calling `on_progress` as the request
+    /// moves through the queue, then returning the results in the same order.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn matrix_with_progress(
+        &self,
+        template: &str,
+        parameters: Vec<Parameters>,
+        on_progress: impl FnMut(Progress) + Send,
+    ) -> Result<Vec<MatrixResult>, Error> {
+        self.submit(Request::Matrix(MatrixRequest {
+            template: template.to_string(),
+            parameters,
+        }))
+        .await?
+        .wait_with_progress(on_progress)
+        .await?
+        .into_matrix()
+    }
+
+    /// Send a request to the server without waiting for it.
+    ///
+    /// Several requests can be submitted before waiting on any of them,
+    /// the server queues them all and answers each as it finishes.
+    ///
+    /// # Errors
+    /// Returns an error if the request could not be sent.
+    pub async fn submit(&self, request: Request) -> Result<AsyncPending, Error> {
+        self.submit_with_options(request, Options::default()).await
+    }
+
+    /// Send a request to the server with [`Options`], without waiting for it.
+    ///
+    /// # Errors
+    /// Returns an error if the request could not be sent.
+    pub async fn submit_with_options(
+        &self,
+        request: Request,
+        options: Options,
+    ) -> Result<AsyncPending, Error> {
+        let (id, responses) = self.register()?;
+        self.send(
+            id,
+            responses,
+            &Command::Submit {
+                id,
+                request,
+                options,
+            },
+        )
+        .await
+    }
+
+    /// Read the server's history of benchmarked scripts, oldest first.
+    ///
+    /// # Errors
+    /// Returns an error if the server has no history, or the query fails.
+    pub async fn history(&self, query: HistoryQuery) -> Result<Vec<HistoryRecord>, Error> {
+        let (id, responses) = self.register()?;
+        self.send(id, responses, &Command::History { id, query })
+            .await?
+            .wait()
+            .await?
+            .into_history()
+    }
+
+    /// Pick an ID for a new request and start listening for its responses.
+    fn register(&self) -> Result<(RequestId, mpsc::UnboundedReceiver<Response>), Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, responses) = mpsc::unbounded_channel();
+        let mut waiting = self
+            .shared
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if waiting.closed {
+            return Err(Error::Disconnected);
+        }
+        waiting.requests.insert(id, sender);
+        drop(waiting);
+        Ok((id, responses))
+    }
+
+    /// Write the message that starts the registered request `id`.
+    async fn send(
+        &self,
+        id: RequestId,
+        responses: mpsc::UnboundedReceiver<Response>,
+        message: &impl Message,
+    ) -> Result<AsyncPending, Error> {
+        let mut writer = self.shared.writer.lock().await;
+        let result = message.write_async(&mut *writer).await;
+        drop(writer);
+        if let Err(e) = result {
+            self.shared.finish(Some(id));
+            return Err(e);
+        }
+        Ok(AsyncPending {
+            id,
+            responses,
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Cancel a request submitted on this client, from any task.
+    ///
+    /// The cancelled request returns [`Error::Cancelled`].
+    /// Returns `false` if the request has already finished.
+    ///
+    /// # Errors
+    /// Returns an error if the cancellation could not be sent.
+    pub async fn cancel(&self, id: RequestId) -> Result<bool, Error> {
+        self.shared.cancel(id).await
+    }
+}
+
+impl Drop for AsyncClient {
+    fn drop(&mut self) {
+        // Fails anything still pending.
+        self.reader.abort();
+    }
+}
+
+async fn read_responses(closer: Closer, mut reader: OwnedReadHalf) {
+    let shared = &closer.0;
+    while let Ok(Reply { id, response }) = Reply::from_async_reader(&mut reader).await {
+        let finished = response.is_final();
+        let mut waiting = shared
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // A reply without an ID is for every pending request.
+        for (_, sender) in waiting
+            .requests
+            .iter()
+            .filter(|(request, _)| id.is_none_or(|id| id == **request))
+        {
+            // The pending request may have been dropped without waiting.
+            let _ = sender.send(response.clone());
+        }
+        if finished {
+            // Still under the lock, so a request can't be cancelled once its response is in.
+            waiting.finish(id);
+        }
+        drop(waiting);
+    }
+}
+
+impl Shared {
+    /// Stop waiting on a request, or on every request for `None`.
+    fn finish(&self, id: Option<RequestId>) {
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        waiting.finish(id);
+        drop(waiting);
+    }
+
+    async fn cancel(&self, id: RequestId) -> Result<bool, Error> {
+        let pending = self
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .requests
+            .contains_key(&id);
+        if !pending {
+            return Ok(false);
+        }
+        Command::Cancel(id)
+            .write_async(&mut *self.writer.lock().await)
+            .await?;
+        Ok(true)
+    }
+}
+
+/// A request submitted with [`AsyncClient::submit`], waiting for its response.
+pub struct AsyncPending {
+    id: RequestId,
+    responses: mpsc::UnboundedReceiver<Response>,
+    shared: Arc<Shared>,
+}
+
+impl AsyncPending {
+    /// The ID the request was submitted with.
+    #[must_use]
+    pub const fn id(&self) -> RequestId {
+        self.id
+    }
+
+    /// Cancel the request, see [`AsyncClient::cancel`].
+    ///
+    /// # Errors
+    /// Returns an error if the cancellation could not be sent.
+    pub async fn cancel(&self) -> Result<bool, Error> {
+        self.shared.cancel(self.id).await
+    }
+
+    /// Wait for the response.
+    ///
+    /// # Errors
+    /// Returns an error if the connection is lost.
+    pub async fn wait(self) -> Result<Response, Error> {
+        self.wait_with_progress(|_| {}).await
+    }
+
+    /// Wait for the response, calling `on_progress` as the request moves through the queue.
+    ///
+    /// Dropping the future, such as when it times out, stops waiting for the response.
+    ///
+    /// # Errors
+    /// Returns an error if the connection is lost.
+    pub async fn wait_with_progress(
+        mut self,
+        mut on_progress: impl FnMut(Progress) + Send,
+    ) -> Result<Response, Error> {
+        let _waiting = Waited {
+            shared: &self.shared,
+            id: self.id,
+        };
+        loop {
+            match self.responses.recv().await.ok_or(Error::Disconnected)? {
+                Response::Progress(progress) => on_progress(progress),
+                response => return Ok(response),
+            }
+        }
+    }
+}
+
+/// Stops waiting on a request when dropped, so one given up on is not kept.
+struct Waited<'a> {
+    shared: &'a Shared,
+    id: RequestId,
+}
+
+impl Drop for Waited<'_> {
+    fn drop(&mut self) {
+        self.shared.finish(Some(self.id));
+    }
+}
@@ -0,0 +1,136 @@
+use std::{collections::BTreeMap, fmt::Display, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Report, Statistics};
+
+/// How much slower or faster than its baseline a script may be before it counts as changed,
+/// as a fraction of the baseline's mean.
+pub const DEFAULT_TOLERANCE: f64 = 0.05;
+
+/// Results saved from an earlier run, to compare later runs against.
+///
+/// Scripts are keyed by the branch they ran on, the name of their [`Group`](crate::Group),
+/// and their own name, so the same file can hold every branch and benchmark of a project.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Baseline {
+    pub branches: BTreeMap<String, BTreeMap<String, BTreeMap<String, Statistics>>>,
+}
+
+/// How an [`Entry`](crate::Entry) compares to its baseline, displayed such as `+12.5% (regression)`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Change {
+    /// The mean of the baseline, in milliseconds.
+    pub baseline: f64,
+    /// The mean of the entry divided by that of the baseline, above 1 when slower.
+    pub ratio: f64,
+    pub status: ChangeStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeStatus {
+    /// Slower than the baseline by more than the tolerance.
+    Regression,
+    /// Faster than the baseline by more than the tolerance.
+    Improvement,
+    Unchanged,
+}
+
+impl Display for ChangeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Regression => write!(f, "regression"),
+            Self::Improvement => write!(f, "improvement"),
+            Self::Unchanged => write!(f, "unchanged"),
+        }
+    }
+}
+
+impl Baseline {
+    /// Read the baseline at `path`, or an empty one if there is no file yet.
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be read or is not a baseline.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match std::fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| Error::InvalidBaseline(format!("{}: {e}", path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::Io(e)),
+        }
+    }
+
+    /// # Errors
+    /// Returns an error if the file can not be written.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut content =
+            serde_json::to_vec_pretty(self).map_err(|e| Error::InvalidBaseline(e.to_string()))?;
+        content.push(b'\n');
+        std::fs::write(path, content).map_err(Error::Io)
+    }
+
+    /// Save every script of `report`, replacing what was saved for them before.
+    pub fn record(&mut self, report: &Report) {
+        for group in &report.groups {
+            let saved = self
+                .branches
+                .entry(group.branch.clone().unwrap_or_default())
+                .or_default()
+                .entry(group.name.clone())
+                .or_default();
+            for entry in &group.entries {
+                saved.insert(entry.name.clone(), entry.statistics.clone());
+            }
+        }
+    }
+
+    /// The saved statistics of `script` in `group` on `branch`.
+    #[must_use]
+    pub fn get(&self, branch: &str, group: &str, script: &str) -> Option<&Statistics> {
+        self.branches.get(branch)?.get(group)?.get(script)
+    }
+
+    /// Compare every script of `report` that has a baseline against it,
+    /// failing those that are slower by more than `tolerance`, such as 0.05 for 5%.
+    pub fn compare(&self, report: &mut Report, tolerance: f64) {
+        for group in &mut report.groups {
+            let branch = group.branch.clone().unwrap_or_default();
+            for entry in &mut group.entries {
+                let Some(saved) = self
+                    .get(&branch, &group.name, &entry.name)
+                    .filter(|saved| saved.mean > 0.0)
+                else {
+                    continue;
+                };
+                let ratio = entry.statistics.mean / saved.mean;
+                let status = if ratio > 1.0 + tolerance {
+                    ChangeStatus::Regression
+                } else if ratio < 1.0 - tolerance {
+                    ChangeStatus::Improvement
+                } else {
+                    ChangeStatus::Unchanged
+                };
+                if status == ChangeStatus::Regression {
+                    entry.failures.push(format!(
+                        "mean of {:.6} ms is {:.1}% slower than the baseline of {:.6} ms",
+                        entry.statistics.mean,
+                        (ratio - 1.0) * 100.0,
+                        saved.mean
+                    ));
+                }
+                entry.change = Some(Change {
+                    baseline: saved.mean,
+                    ratio,
+                    status,
+                });
+            }
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:+.1}% ({})", (self.ratio - 1.0) * 100.0, self.status)
+    }
+}
@@ -1,111 +1,428 @@
 use std::{
+    collections::HashMap,
     io::{Read, Write},
-    net::TcpStream,
-    sync::Mutex,
+    net::{Shutdown, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
 };
 
 use crate::{
-    CompareRequest, CompareResult, ExecuteResult, Message, Request, Response, ServerConfig,
-    DEFAULT_PORT, HEADER_ID,
+    ClientHello, Command, CompareRequest, CompareResult, Error, ExecuteResult, HistoryQuery,
+    HistoryRecord, MatrixRequest, MatrixResult, Message, Options, Parameters, Progress, Protocol,
+    Reply, Request, RequestId, Response, ServerConfig, ServerHello, DEFAULT_PORT, HEADER_ID,
+    LEGACY_HEADER_ID,
 };
 
 pub struct Client {
-    stream: Mutex<TcpStream>,
+    shared: Arc<Shared>,
+    protocol: Protocol,
+    next_id: AtomicU64,
+    timeout: Mutex<Option<Duration>>,
+}
+
+/// State shared between the client, its pending requests, and the thread reading responses.
+struct Shared {
+    writer: Mutex<TcpStream>,
+    waiting: Mutex<Waiting>,
+}
+
+#[derive(Default)]
+struct Waiting {
+    requests: HashMap<RequestId, mpsc::Sender<Response>>,
+    closed: bool,
+}
+
+impl Waiting {
+    fn finish(&mut self, id: Option<RequestId>) {
+        match id {
+            Some(id) => {
+                self.requests.remove(&id);
+            }
+            None => self.requests.clear(),
+        }
+    }
 }
 
 impl Client {
     /// Connect to the server with a specific port.
     ///
     /// # Errors
-    /// Returns a string error if the connection fails.
-    ///
-    /// # Panics
-    /// Panics on TCP stream errors.
-    pub fn connect_with_port(host: &str, port: u16, config: &ServerConfig) -> Result<Self, String> {
-        let mut stream = TcpStream::connect(format!("{host}:{port}")).expect("Failed to connect");
+    /// Returns an error if the connection or handshake fails.
+    pub fn connect_with_port(host: &str, port: u16, config: &ServerConfig) -> Result<Self, Error> {
+        let mut stream = TcpStream::connect(format!("{host}:{port}"))?;
         // Send the header ID to the server.
-        stream
-            .write_all(HEADER_ID)
-            .expect("Failed to send header ID");
+        stream.write_all(HEADER_ID)?;
 
         // Expect the server to echo the header ID back to us.
         let mut buf = [0; 16];
-        stream
-            .read_exact(&mut buf)
-            .expect("Failed to read header ID");
+        stream.read_exact(&mut buf)?;
+        if buf == *LEGACY_HEADER_ID {
+            return Err(Error::UnsupportedVersion {
+                min_version: 1,
+                max_version: 1,
+            });
+        }
         if buf != *HEADER_ID {
-            return Err("Invalid header ID".to_string());
+            return Err(Error::HandshakeMismatch);
         }
 
+        // Agree on a protocol version and capabilities.
+        ClientHello::default().write(&mut stream)?;
+        let protocol = ServerHello::from_reader(&mut stream)?.into_protocol()?;
+
         // Send the server config to the server.
-        config
-            .write(&mut stream)
-            .expect("Failed to send server config");
+        config.write(&mut stream)?;
 
         // Expect a 1 for wait, disconnect if not.
         let mut buf = [0; 1];
-        stream.read_exact(&mut buf).expect("Failed to read ACK");
+        stream.read_exact(&mut buf)?;
         if buf[0] != 1 {
-            return Err("Invalid ACK".to_string());
+            return Err(Error::InvalidAck(buf[0]));
         }
 
+        let shared = Arc::new(Shared {
+            writer: Mutex::new(stream.try_clone()?),
+            waiting: Mutex::default(),
+        });
+        let reader = shared.clone();
+        std::thread::spawn(move || reader.read_responses(stream));
+
         Ok(Self {
-            stream: Mutex::new(stream),
+            shared,
+            protocol,
+            next_id: AtomicU64::new(0),
+            timeout: Mutex::new(None),
         })
     }
 
     /// Connect to the server with the default port.
     ///
     /// # Errors
-    /// Returns a string error if the connection fails.
-    ///
-    /// # Panics
-    /// Panics on TCP stream errors.
-    pub fn connect(host: &str, config: &ServerConfig) -> Result<Self, String> {
+    /// Returns an error if the connection or handshake fails.
+    pub fn connect(host: &str, config: &ServerConfig) -> Result<Self, Error> {
         Self::connect_with_port(host, DEFAULT_PORT, config)
     }
 
+    /// Set how long to wait for the server before failing with [`Error::Timeout`].
+    ///
+    /// `None` waits indefinitely, which is the default.
+    /// Applies to requests submitted after it is set.
+    ///
+    /// # Errors
+    /// Returns an error if the timeout could not be applied to the socket.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        let stream = self
+            .shared
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        stream.set_write_timeout(timeout)?;
+        drop(stream);
+        *self.timeout.lock().unwrap_or_else(PoisonError::into_inner) = timeout;
+        Ok(())
+    }
+
+    /// The protocol version and capabilities negotiated with the server.
+    #[must_use]
+    pub const fn protocol(&self) -> &Protocol {
+        &self.protocol
+    }
+
     /// Execute a script on the server, returning the benchmark and result.
     ///
     /// # Errors
-    /// Returns a string error if the request fails.
-    ///
-    /// # Panics
-    /// Panics on TCP stream errors.
-    pub fn execute(&self, content: &str) -> Result<ExecuteResult, String> {
-        let result = {
-            let mut stream = self.stream.lock().expect("Failed to lock stream");
-            Request::Execute(content.to_string())
-                .write(&mut *stream)
-                .expect("Failed to send request");
-            Response::from_reader(&mut *stream).expect("Failed to read response")
-        };
-        match result {
-            Response::Execute(Ok(res)) => Ok(res),
-            Response::Execute(Err(err)) | Response::Error(err) => Err(err),
-            _ => Err("Invalid response".to_string()),
-        }
+    /// Returns an error if the request fails.
+    pub fn execute(&self, content: &str) -> Result<ExecuteResult, Error> {
+        self.execute_with_progress(content, |_| {})
+    }
+
+    /// Execute a script on the server, calling `on_progress` as the request moves
+    /// through the queue, then returning the benchmark and result.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn execute_with_progress(
+        &self,
+        content: &str,
+        on_progress: impl FnMut(Progress),
+    ) -> Result<ExecuteResult, Error> {
+        self.submit(Request::Execute(content.to_string()))?
+            .wait_with_progress(on_progress)?
+            .into_execute()
     }
 
     /// Compare multiple scripts on the server, returning the benchmarks and results.
     ///
     /// # Errors
-    /// Returns a string error if the request fails.
-    ///
-    /// # Panics
-    /// Panics on TCP stream errors.
-    pub fn compare(&self, requests: Vec<CompareRequest>) -> Result<Vec<CompareResult>, String> {
-        let result = {
-            let mut stream = self.stream.lock().expect("Failed to lock stream");
-            Request::Compare(requests)
-                .write(&mut *stream)
-                .expect("Failed to write request");
-            Response::from_reader(&mut *stream).expect("Failed to read response")
-        };
-        match result {
-            Response::Compare(Ok(result)) => Ok(result),
-            Response::Compare(Err(err)) | Response::Error(err) => Err(err),
-            _ => Err("Invalid response".to_string()),
+    /// Returns an error if the request fails.
+    pub fn compare(&self, requests: Vec<CompareRequest>) -> Result<Vec<CompareResult>, Error> {
+        self.compare_with_progress(requests, |_| {})
+    }
+
+    /// Compare multiple scripts on the server, calling `on_progress` as the request
+    /// moves through the queue, then returning the benchmarks and results.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn compare_with_progress(
+        &self,
+        requests: Vec<CompareRequest>,
+        on_progress: impl FnMut(Progress),
+    ) -> Result<Vec<CompareResult>, Error> {
+        self.submit(Request::Compare(requests))?
+            .wait_with_progress(on_progress)?
+            .into_compare()
+    }
+
+    /// Benchmark a script with each set of parameters, returning the results in the same order.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn matrix(
+        &self,
+        template: &str,
+        parameters: Vec<Parameters>,
+    ) -> Result<Vec<MatrixResult>, Error> {
+        self.matrix_with_progress(template, parameters, |_| {})
+    }
+
+    /// Benchmark a script with each set of parameters, calling `on_progress` as the request
+    /// moves through the queue, then returning the results in the same order.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn matrix_with_progress(
+        &self,
+        template: &str,
+        parameters: Vec<Parameters>,
+        on_progress: impl FnMut(Progress),
+    ) -> Result<Vec<MatrixResult>, Error> {
+        self.submit(Request::Matrix(MatrixRequest {
+            template: template.to_string(),
+            parameters,
+        }))?
+        .wait_with_progress(on_progress)?
+        .into_matrix()
+    }
+
+    /// Send a request to the server without waiting for it.
+    ///
+    /// Several requests can be submitted before waiting on any of them,
+    /// the server queues them all and answers each as it finishes.
+    ///
+    /// # Errors
+    /// Returns an error if the request could not be sent.
+    pub fn submit(&self, request: Request) -> Result<Pending, Error> {
+        self.submit_with_options(request, Options::default())
+    }
+
+    /// Send a request to the server with [`Options`], without waiting for it.
+    ///
+    /// # Errors
+    /// Returns an error if the request could not be sent.
+    pub fn submit_with_options(
+        &self,
+        request: Request,
+        options: Options,
+    ) -> Result<Pending, Error> {
+        let (id, responses) = self.register()?;
+        self.send(
+            id,
+            responses,
+            &Command::Submit {
+                id,
+                request,
+                options,
+            },
+        )
+    }
+
+    /// Read the server's history of benchmarked scripts, oldest first.
+    ///
+    /// # Errors
+    /// Returns an error if the server has no history, or the query fails.
+    pub fn history(&self, query: HistoryQuery) -> Result<Vec<HistoryRecord>, Error> {
+        let (id, responses) = self.register()?;
+        self.send(id, responses, &Command::History { id, query })?
+            .wait()?
+            .into_history()
+    }
+
+    /// Pick an ID for a new request and start listening for its responses.
+    fn register(&self) -> Result<(RequestId, mpsc::Receiver<Response>), Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, responses) = mpsc::channel();
+        let mut waiting = self
+            .shared
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if waiting.closed {
+            return Err(Error::Disconnected);
+        }
+        waiting.requests.insert(id, sender);
+        drop(waiting);
+        Ok((id, responses))
+    }
+
+    /// Write the message that starts the registered request `id`.
+    fn send(
+        &self,
+        id: RequestId,
+        responses: mpsc::Receiver<Response>,
+        message: &impl Message,
+    ) -> Result<Pending, Error> {
+        let mut writer = self
+            .shared
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let result = message.write(&mut *writer);
+        drop(writer);
+        if let Err(e) = result {
+            self.shared.finish(Some(id));
+            return Err(e);
+        }
+        Ok(Pending {
+            id,
+            responses,
+            shared: self.shared.clone(),
+            timeout: *self.timeout.lock().unwrap_or_else(PoisonError::into_inner),
+        })
+    }
+
+    /// Cancel a request submitted on this client, from any thread.
+    ///
+    /// The cancelled request returns [`Error::Cancelled`].
+    /// Returns `false` if the request has already finished.
+    ///
+    /// # Errors
+    /// Returns an error if the cancellation could not be sent.
+    pub fn cancel(&self, id: RequestId) -> Result<bool, Error> {
+        self.shared.cancel(id)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        // Stops the reader thread, failing anything still pending.
+        let writer = self
+            .shared
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let _ = writer.shutdown(Shutdown::Both);
+        drop(writer);
+    }
+}
+
+impl Shared {
+    fn read_responses(&self, mut stream: TcpStream) {
+        while let Ok(Reply { id, response }) = Reply::from_reader(&mut stream) {
+            let finished = response.is_final();
+            let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+            // A reply without an ID is for every pending request.
+            for (_, sender) in waiting
+                .requests
+                .iter()
+                .filter(|(request, _)| id.is_none_or(|id| id == **request))
+            {
+                // The pending request may have been dropped without waiting.
+                let _ = sender.send(response.clone());
+            }
+            if finished {
+                // Still under the lock, so a request can't be cancelled once its response is in.
+                waiting.finish(id);
+            }
+            drop(waiting);
+        }
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        waiting.closed = true;
+        waiting.requests.clear();
+        drop(waiting);
+    }
+
+    /// Stop waiting on a request, or on every request for `None`.
+    fn finish(&self, id: Option<RequestId>) {
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        waiting.finish(id);
+        drop(waiting);
+    }
+
+    fn cancel(&self, id: RequestId) -> Result<bool, Error> {
+        let waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        let pending = waiting.requests.contains_key(&id);
+        drop(waiting);
+        if !pending {
+            return Ok(false);
+        }
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        Command::Cancel(id).write(&mut *writer)?;
+        drop(writer);
+        Ok(true)
+    }
+}
+
+/// A request submitted with [`Client::submit`], waiting for its response.
+pub struct Pending {
+    id: RequestId,
+    responses: mpsc::Receiver<Response>,
+    shared: Arc<Shared>,
+    timeout: Option<Duration>,
+}
+
+impl Pending {
+    /// The ID the request was submitted with.
+    #[must_use]
+    pub const fn id(&self) -> RequestId {
+        self.id
+    }
+
+    /// Cancel the request, see [`Client::cancel`].
+    ///
+    /// # Errors
+    /// Returns an error if the cancellation could not be sent.
+    pub fn cancel(&self) -> Result<bool, Error> {
+        self.shared.cancel(self.id)
+    }
+
+    /// Wait for the response.
+    ///
+    /// # Errors
+    /// Returns an error if the connection is lost or the timeout is reached.
+    pub fn wait(self) -> Result<Response, Error> {
+        self.wait_with_progress(|_| {})
+    }
+
+    /// Wait for the response, calling `on_progress` as the request moves through the queue.
+    ///
+    /// # Errors
+    /// Returns an error if the connection is lost or the timeout is reached.
+    pub fn wait_with_progress(
+        self,
+        mut on_progress: impl FnMut(Progress),
+    ) -> Result<Response, Error> {
+        loop {
+            let response = match self.timeout {
+                Some(timeout) => match self.responses.recv_timeout(timeout) {
+                    Ok(response) => response,
+                    Err(RecvTimeoutError::Timeout) => {
+                        // Nothing will read the response, so stop waiting for it.
+                        self.shared.finish(Some(self.id));
+                        return Err(Error::Timeout);
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return Err(Error::Disconnected),
+                },
+                None => self.responses.recv().map_err(|_| Error::Disconnected)?,
+            };
+            match response {
+                Response::Progress(progress) => on_progress(progress),
+                response => return Ok(response),
+            }
         }
     }
 }
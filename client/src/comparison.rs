@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{stats::welch, CompareResult, Interval, Statistics};
+
+/// How sure a [`Comparison`] must be before calling a difference significant.
+pub const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+/// Compare results against a baseline, from their repeated samples.
+///
+/// Each script is tested against the baseline with Welch's t-test,
+/// which does not assume they vary by the same amount.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Comparison {
+    /// The [`CompareRequest::id`](crate::CompareRequest::id) of the baseline.
+    pub baseline: u16,
+    /// Such as 0.95, for the intervals and verdicts.
+    pub confidence: f64,
+    /// Every script including the baseline, fastest first.
+    pub ranking: Vec<Ranked>,
+}
+
+/// One script in a [`Comparison`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Ranked {
+    pub id: u16,
+    /// 1 for the fastest.
+    pub rank: usize,
+    pub statistics: Statistics,
+    /// The range its mean time is in.
+    pub interval: Interval,
+    /// How many times faster than the baseline, below 1 is slower.
+    pub speedup: f64,
+    /// The range the difference from the baseline's mean time is in, negative is faster.
+    pub difference: Interval,
+    /// The chance of a difference this large if both were as fast,
+    /// `None` for the baseline or with fewer than two samples each.
+    pub p_value: Option<f64>,
+    pub verdict: Verdict,
+}
+
+/// Whether a script is faster than the baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Verdict {
+    Baseline,
+    Faster,
+    Slower,
+    /// The difference could be noise, more repetitions may tell.
+    Inconclusive,
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Baseline => "baseline",
+            Self::Faster => "faster",
+            Self::Slower => "slower",
+            Self::Inconclusive => "inconclusive",
+        })
+    }
+}
+
+impl Comparison {
+    /// Compare `results` against the one with the ID `baseline`,
+    /// or `None` if it is not among them.
+    #[must_use]
+    pub fn new(results: &[CompareResult], baseline: u16) -> Option<Self> {
+        Self::with_confidence(results, baseline, DEFAULT_CONFIDENCE)
+    }
+
+    /// Compare `results` against the one with the ID `baseline`, with a `confidence` such as 0.99.
+    #[must_use]
+    pub fn with_confidence(
+        results: &[CompareResult],
+        baseline: u16,
+        confidence: f64,
+    ) -> Option<Self> {
+        let base = results
+            .iter()
+            .find(|result| result.id == baseline)?
+            .statistics();
+        let mut ranking = results
+            .iter()
+            .map(|result| {
+                let statistics = result.statistics();
+                let (difference, p_value) = if result.id == baseline {
+                    (
+                        Interval {
+                            low: 0.0,
+                            high: 0.0,
+                        },
+                        None,
+                    )
+                } else {
+                    welch(&base, &statistics, confidence)
+                };
+                let verdict = match p_value {
+                    _ if result.id == baseline => Verdict::Baseline,
+                    Some(p) if p < 1.0 - confidence && statistics.mean < base.mean => {
+                        Verdict::Faster
+                    }
+                    Some(p) if p < 1.0 - confidence => Verdict::Slower,
+                    _ => Verdict::Inconclusive,
+                };
+                Ranked {
+                    id: result.id,
+                    rank: 0,
+                    interval: statistics.interval(confidence),
+                    speedup: base.mean / statistics.mean,
+                    difference,
+                    p_value,
+                    verdict,
+                    statistics,
+                }
+            })
+            .collect::<Vec<_>>();
+        ranking.sort_by(|a, b| a.statistics.mean.total_cmp(&b.statistics.mean));
+        for (rank, ranked) in (1..).zip(&mut ranking) {
+            ranked.rank = rank;
+        }
+        Some(Self {
+            baseline,
+            confidence,
+            ranking,
+        })
+    }
+
+    /// The script with the ID `id`.
+    #[must_use]
+    pub fn get(&self, id: u16) -> Option<&Ranked> {
+        self.ranking.iter().find(|ranked| ranked.id == id)
+    }
+}
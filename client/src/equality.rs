@@ -0,0 +1,80 @@
+use arma_rs::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::to_sqf;
+
+/// Check that compared scripts return the same as one of them, see [`Options::assert_equal`](crate::Options::assert_equal).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AssertEqual {
+    /// The [`CompareRequest::id`](crate::CompareRequest::id) of the script the others must match.
+    pub baseline: u16,
+    /// How far apart two numbers may be and still be equal.
+    pub tolerance: f64,
+}
+
+/// Where a script returned something other than the baseline.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Mismatch {
+    /// The indices into nested arrays, empty when the whole value differs.
+    pub path: Vec<usize>,
+    pub expected: Value,
+    pub actual: Value,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for index in &self.path {
+            write!(f, "[{index}]")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, ": ")?;
+        }
+        let sqf = |value: &Value| to_sqf(value).unwrap_or_else(|| value.to_string());
+        write!(
+            f,
+            "expected {}, got {}",
+            sqf(&self.expected),
+            sqf(&self.actual)
+        )
+    }
+}
+
+/// Every place `actual` differs from `expected`,
+/// treating numbers within `tolerance` of each other as equal.
+#[must_use]
+pub fn mismatches(expected: &Value, actual: &Value, tolerance: f64) -> Vec<Mismatch> {
+    let mut found = Vec::new();
+    compare(expected, actual, tolerance, &mut Vec::new(), &mut found);
+    found
+}
+
+fn compare(
+    expected: &Value,
+    actual: &Value,
+    tolerance: f64,
+    path: &mut Vec<usize>,
+    found: &mut Vec<Mismatch>,
+) {
+    let equal = match (expected, actual) {
+        // Infinities and NaN are only equal to themselves.
+        (Value::Number(a), Value::Number(b)) => {
+            a.total_cmp(b).is_eq() || (a - b).abs() <= tolerance
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (index, (a, b)) in a.iter().zip(b).enumerate() {
+                path.push(index);
+                compare(a, b, tolerance, path, found);
+                path.pop();
+            }
+            return;
+        }
+        (a, b) => a == b,
+    };
+    if !equal {
+        found.push(Mismatch {
+            path: path.clone(),
+            expected: expected.clone(),
+            actual: actual.clone(),
+        });
+    }
+}
@@ -0,0 +1,80 @@
+use std::io::ErrorKind;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(std::io::Error),
+    #[error("header ID did not match")]
+    HandshakeMismatch,
+    #[error(
+        "server only supports protocol versions {min_version} to {max_version}, \
+        this client supports {} to {}",
+        crate::MIN_PROTOCOL_VERSION,
+        crate::PROTOCOL_VERSION
+    )]
+    UnsupportedVersion { min_version: u16, max_version: u16 },
+    #[error("invalid ACK: {0}")]
+    InvalidAck(u8),
+    #[error("failed to decode message: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+    #[error("failed to encode message: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("message of {size} bytes exceeds the maximum of {max} bytes")]
+    MessageTooLarge { size: u64, max: u64 },
+    #[error("server error: {0}")]
+    ServerError(String),
+    #[error("connection to the server was lost")]
+    Disconnected,
+    #[error("request was cancelled")]
+    Cancelled,
+    #[error("Arma crashed (exit code {exit_code:?}, signal {signal:?})")]
+    Crashed {
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+        logs: Vec<String>,
+    },
+    #[error("Arma exited before the benchmark started")]
+    NeverStarted { logs: Vec<String> },
+    #[error("Arma exited without writing a result")]
+    ResultMissing { logs: Vec<String> },
+    #[error("benchmark timed out after {seconds} seconds")]
+    RunTimedOut { seconds: u64, logs: Vec<String> },
+    #[error("{file} failed to compile")]
+    CompileFailed {
+        file: String,
+        errors: Vec<crate::ScriptError>,
+        logs: Vec<String>,
+    },
+    #[error("timed out waiting for the server")]
+    Timeout,
+    #[error("unexpected response from server")]
+    UnexpectedResponse,
+    #[error("invalid suite: {0}")]
+    InvalidSuite(String),
+    #[error("invalid baseline: {0}")]
+    InvalidBaseline(String),
+}
+
+impl Error {
+    /// What Arma logged before the run failed, empty for errors that did not come from a run.
+    #[must_use]
+    pub fn logs(&self) -> &[String] {
+        match self {
+            Self::Crashed { logs, .. }
+            | Self::NeverStarted { logs }
+            | Self::ResultMissing { logs }
+            | Self::RunTimedOut { logs, .. }
+            | Self::CompileFailed { logs, .. } => logs,
+            _ => &[],
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => Self::Timeout,
+            _ => Self::Io(err),
+        }
+    }
+}
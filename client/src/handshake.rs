@@ -0,0 +1,97 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Message};
+
+/// The newest protocol version this crate speaks.
+pub static PROTOCOL_VERSION: u16 = 2;
+/// The oldest protocol version this crate still speaks.
+pub static MIN_PROTOCOL_VERSION: u16 = 2;
+/// Optional protocol features this crate supports, advertised during the handshake.
+pub static CAPABILITIES: &[&str] = &[CAPABILITY_PROGRESS];
+
+/// The server sends [`Response::Progress`](crate::Response::Progress) updates before the final response.
+pub const CAPABILITY_PROGRESS: &str = "progress";
+
+/// Sent by the client after the header ID, describing what it can speak.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClientHello {
+    pub min_version: u16,
+    pub max_version: u16,
+    pub capabilities: Vec<String>,
+}
+
+impl Default for ClientHello {
+    fn default() -> Self {
+        Self {
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            capabilities: CAPABILITIES.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+impl Message for ClientHello {}
+
+/// The server's answer to a [`ClientHello`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum ServerHello {
+    /// The connection will use `version`, and the capabilities both sides support.
+    Accepted(Protocol),
+    /// None of the client's versions are supported, the server will disconnect.
+    UnsupportedVersion { min_version: u16, max_version: u16 },
+}
+
+impl Message for ServerHello {}
+
+impl ServerHello {
+    /// Pick the newest version and the shared capabilities for a client,
+    /// given the versions the server accepts.
+    #[must_use]
+    pub fn negotiate(hello: &ClientHello, versions: &RangeInclusive<u16>) -> Self {
+        let version = hello.max_version.min(*versions.end());
+        if version < hello.min_version.max(*versions.start()) {
+            return Self::UnsupportedVersion {
+                min_version: *versions.start(),
+                max_version: *versions.end(),
+            };
+        }
+        Self::Accepted(Protocol {
+            version,
+            capabilities: hello
+                .capabilities
+                .iter()
+                .filter(|c| CAPABILITIES.contains(&c.as_str()))
+                .cloned()
+                .collect(),
+        })
+    }
+
+    pub(crate) fn into_protocol(self) -> Result<Protocol, Error> {
+        match self {
+            Self::Accepted(protocol) => Ok(protocol),
+            Self::UnsupportedVersion {
+                min_version,
+                max_version,
+            } => Err(Error::UnsupportedVersion {
+                min_version,
+                max_version,
+            }),
+        }
+    }
+}
+
+/// The protocol negotiated for a connection.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Protocol {
+    pub version: u16,
+    pub capabilities: Vec<String>,
+}
+
+impl Protocol {
+    #[must_use]
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
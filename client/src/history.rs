@@ -0,0 +1,74 @@
+use std::fmt::Write;
+
+use arma_rs::Value;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Options, Parameters, ServerConfig, Statistics, Timing};
+
+/// Which runs [`Client::history`](crate::Client::history) returns, every filter that is set must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HistoryQuery {
+    /// Only runs of the script with this [`script_hash`].
+    pub hash: Option<String>,
+    /// Only runs that finished at or after this many seconds since the Unix epoch.
+    pub since: Option<u64>,
+    /// Only runs that finished before this many seconds since the Unix epoch.
+    pub until: Option<u64>,
+    /// The most records to return, keeping the newest.
+    /// The server has a limit of its own, which applies when this is `None` or higher.
+    pub limit: Option<u32>,
+    /// Include what Arma logged, which can be large. Without it [`HistoryRecord::logs`] is empty.
+    #[serde(default)]
+    pub logs: bool,
+}
+
+/// One script the server benchmarked, kept in its history.
+///
+/// A compare records each of its scripts, and a matrix each of its sets of parameters.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HistoryRecord {
+    /// Increases with every record the server keeps.
+    pub id: u64,
+    /// When the request finished, in seconds since the Unix epoch.
+    pub time: u64,
+    /// The [`script_hash`] of the script.
+    pub hash: String,
+    /// The server the script ran on, without the branch password.
+    pub config: ServerConfig,
+    /// The Arma build, such as `2.18.152405`, if Arma logged it.
+    pub version: Option<String>,
+    /// The parameters of a matrix, empty for other requests.
+    pub parameters: Parameters,
+    /// The options that change what was measured, such as the setup and cycles.
+    /// Those that do not, such as the timeout, are left out.
+    pub options: Options,
+    /// Why the run failed, `None` if it finished.
+    /// A failed run has no samples and its result is nil, but keeps its logs.
+    pub failure: Option<String>,
+    pub iterations: u32,
+    pub timing: Timing,
+    pub samples: Vec<f64>,
+    pub ret: Value,
+    /// Only read when [`HistoryQuery::logs`] is set.
+    pub logs: Vec<String>,
+}
+
+impl HistoryRecord {
+    /// Summarise the time of every repetition, see [`Statistics`].
+    #[must_use]
+    pub fn statistics(&self) -> Option<Statistics> {
+        Statistics::new(self.samples.clone())
+    }
+}
+
+/// The SHA-256 of a script as lowercase hex, which the server keys its history by.
+#[must_use]
+pub fn script_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .fold(String::with_capacity(64), |mut hash, byte| {
+            let _ = write!(hash, "{byte:02x}");
+            hash
+        })
+}
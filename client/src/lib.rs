@@ -1,75 +1,143 @@
+#[cfg(feature = "tokio")]
+mod async_client;
+mod baseline;
 mod client;
+mod comparison;
+mod equality;
+mod error;
+mod handshake;
+mod history;
+mod report;
+mod sqf;
+mod stats;
+mod suite;
 
-use std::io::{Read, Write};
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+};
 
 use arma_rs::Value;
 use rmp_serde::Deserializer;
 use serde::{Deserialize, Serialize};
 
-pub use client::Client;
+#[cfg(feature = "tokio")]
+pub use async_client::{AsyncClient, AsyncPending};
+pub use baseline::{Baseline, Change, ChangeStatus, DEFAULT_TOLERANCE};
+pub use client::{Client, Pending};
+pub use comparison::{Comparison, Ranked, Verdict, DEFAULT_CONFIDENCE};
+pub use equality::{mismatches, AssertEqual, Mismatch};
+pub use error::Error;
+pub use handshake::{
+    ClientHello, Protocol, ServerHello, CAPABILITIES, CAPABILITY_PROGRESS, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
+};
+pub use history::{script_hash, HistoryQuery, HistoryRecord};
+pub use report::{Entry, Group, Report, ReportFormat, Thresholds};
+pub use sqf::to_sqf;
+pub use stats::{Interval, Statistics};
+pub use suite::{Benchmark, Suite, Variant};
 
-/// Sent between the client and server at the start of a connection.
-pub static HEADER_ID: &[u8; 16] = b"ARMABENCH-VER010";
+/// Sent between the client and server at the start of a connection,
+/// before the protocol version is negotiated with [`ClientHello`] and [`ServerHello`].
+pub static HEADER_ID: &[u8; 16] = b"ARMABENCH-PROTO2";
+/// Sent by clients and servers from before the handshake, which speak protocol version 1.
+pub static LEGACY_HEADER_ID: &[u8; 16] = b"ARMABENCH-VER010";
 pub static DEFAULT_PORT: u16 = 7562;
 
+/// The largest message [`Message::from_reader`] and [`Message::from_async_reader`] accept.
+pub static DEFAULT_MAX_MESSAGE_SIZE: u64 = 64 * 1024 * 1024;
+
 pub trait Message: Deserialize<'static> + Serialize + Sync {
     /// Read a message from a reader.
     ///
     /// # Errors
-    /// Returns a string error if the message could not be read.
+    /// Returns an error if the message could not be read or decoded,
+    /// or if it is larger than [`DEFAULT_MAX_MESSAGE_SIZE`].
+    fn from_reader<R: Read>(reader: &'_ mut R) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Self::from_reader_with_limit(reader, DEFAULT_MAX_MESSAGE_SIZE)
+    }
+
+    /// Read a message from a reader, rejecting messages larger than `max` bytes.
     ///
-    /// # Panics
-    /// Panics on I/O errors.
-    fn from_reader<R: Read>(reader: &'_ mut R) -> Result<Self, String>
+    /// The length prefix is checked before any of the payload is read.
+    ///
+    /// # Errors
+    /// Returns an error if the message could not be read or decoded,
+    /// or [`Error::MessageTooLarge`] if it is larger than `max`.
+    fn from_reader_with_limit<R: Read>(reader: &'_ mut R, max: u64) -> Result<Self, Error>
     where
         Self: Sized,
     {
         let mut len_buf = [0; 8];
-        reader.read_exact(&mut len_buf).map_err(|e| e.to_string())?;
-        let len = usize::try_from(u64::from_le_bytes(len_buf)).map_err(|e| e.to_string())?;
-        let mut payload = vec![0; len];
-        reader.read_exact(&mut payload).map_err(|e| e.to_string())?;
-        Deserialize::deserialize(&mut Deserializer::new(payload.as_slice()))
-            .map_err(|e| e.to_string())
+        reader.read_exact(&mut len_buf)?;
+        let len = u64::from_le_bytes(len_buf);
+        if len > max {
+            return Err(Error::MessageTooLarge { size: len, max });
+        }
+        // Grow the buffer as data arrives instead of trusting the length prefix.
+        let mut payload = Vec::new();
+        reader.take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(Deserialize::deserialize(&mut Deserializer::new(
+            payload.as_slice(),
+        ))?)
     }
 
     /// Write a message to a writer.
     ///
-    /// # Errors
-    /// Returns a string error if the message could not be written.
+    /// Structs are written with their field names, so peers ignore fields they don't know about.
     ///
-    /// # Panics
-    /// Panics on I/O errors.
-    fn write<W: Write>(&self, writer: &mut W) -> Result<(), String> {
-        let payload = rmp_serde::to_vec(self).map_err(|e| e.to_string())?;
+    /// # Errors
+    /// Returns an error if the message could not be encoded or written.
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let payload = rmp_serde::to_vec_named(self)?;
         let mut len_buf = [0; 8];
         len_buf.copy_from_slice(&(payload.len() as u64).to_le_bytes());
-        writer.write_all(&len_buf).map_err(|e| e.to_string())?;
-        writer.write_all(&payload).map_err(|e| e.to_string())
+        writer.write_all(&len_buf)?;
+        writer.write_all(&payload)?;
+        Ok(())
     }
 
     #[cfg(feature = "tokio")]
     fn from_async_reader<R: tokio::io::AsyncRead + Unpin + Send>(
         reader: &'_ mut R,
-    ) -> impl std::future::Future<Output = Result<Self, String>> + Send
+    ) -> impl std::future::Future<Output = Result<Self, Error>> + Send
+    where
+        Self: Sized,
+    {
+        Self::from_async_reader_with_limit(reader, DEFAULT_MAX_MESSAGE_SIZE)
+    }
+
+    #[cfg(feature = "tokio")]
+    fn from_async_reader_with_limit<R: tokio::io::AsyncRead + Unpin + Send>(
+        reader: &'_ mut R,
+        max: u64,
+    ) -> impl std::future::Future<Output = Result<Self, Error>> + Send
     where
         Self: Sized,
     {
         async move {
             use tokio::io::AsyncReadExt;
             let mut len_buf = [0; 8];
-            reader
-                .read_exact(&mut len_buf)
-                .await
-                .map_err(|e| e.to_string())?;
-            let len = usize::try_from(u64::from_le_bytes(len_buf)).map_err(|e| e.to_string())?;
-            let mut payload = vec![0; len];
-            reader
-                .read_exact(&mut payload)
-                .await
-                .map_err(|e| e.to_string())?;
-            Deserialize::deserialize(&mut Deserializer::new(payload.as_slice()))
-                .map_err(|e| e.to_string())
+            reader.read_exact(&mut len_buf).await?;
+            let len = u64::from_le_bytes(len_buf);
+            if len > max {
+                return Err(Error::MessageTooLarge { size: len, max });
+            }
+            let mut payload = Vec::new();
+            reader.take(len).read_to_end(&mut payload).await?;
+            if payload.len() as u64 != len {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            Ok(Deserialize::deserialize(&mut Deserializer::new(
+                payload.as_slice(),
+            ))?)
         }
     }
 
@@ -77,26 +145,21 @@ pub trait Message: Deserialize<'static> + Serialize + Sync {
     fn write_async<W: tokio::io::AsyncWrite + Unpin + Send>(
         &self,
         writer: &mut W,
-    ) -> impl std::future::Future<Output = Result<(), String>> + Send {
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send {
         async move {
             use tokio::io::AsyncWriteExt;
-            let payload = rmp_serde::to_vec(self).map_err(|e| e.to_string())?;
+            let payload = rmp_serde::to_vec_named(self)?;
             let mut len_buf = [0; 8];
             len_buf.copy_from_slice(&(payload.len() as u64).to_le_bytes());
-            writer
-                .write_all(&len_buf)
-                .await
-                .map_err(|e| e.to_string())?;
-            writer
-                .write_all(&payload)
-                .await
-                .map_err(|e| e.to_string())?;
-            writer.flush().await.map_err(|e| e.to_string())
+            writer.write_all(&len_buf).await?;
+            writer.write_all(&payload).await?;
+            writer.flush().await?;
+            Ok(())
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct ServerConfig {
     pub binary: String,
     pub branch: String,
@@ -119,10 +182,102 @@ impl Message for ServerConfig {}
 pub enum Request {
     Execute(String),
     Compare(Vec<CompareRequest>),
+    Matrix(MatrixRequest),
 }
 
 impl Message for Request {}
 
+/// Chosen by the client to refer to one of its requests.
+pub type RequestId = u64;
+
+/// Sent by the client after the handshake.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Command {
+    /// Queue a request.
+    Submit {
+        id: RequestId,
+        request: Request,
+        /// Left out when it is the default.
+        #[serde(default, skip_serializing_if = "Options::is_default")]
+        options: Options,
+    },
+    /// Remove a request from the queue, or stop it if it is already running.
+    Cancel(RequestId),
+    /// Read the server's history, answered with [`Response::History`].
+    History { id: RequestId, query: HistoryQuery },
+}
+
+impl Message for Command {}
+
+/// Settings for a single request, sent with [`Command::Submit`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Options {
+    /// How many seconds the request may run before it is stopped.
+    ///
+    /// `None` uses the server's default, a timeout over the server's maximum is rejected.
+    pub timeout: Option<u64>,
+    /// Leave Arma's log output out of the response.
+    #[serde(default)]
+    pub skip_logs: bool,
+    /// How many times to benchmark each script every time Arma starts.
+    /// `None` benchmarks them once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repetitions: Option<u32>,
+    /// How many times to start Arma.
+    /// `None` starts it once, the timeout applies to each start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boots: Option<u32>,
+    /// Check every compared script returns the same as the baseline,
+    /// reporting any differences in [`CompareResult::mismatches`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assert_equal: Option<AssertEqual>,
+    /// SQF run before benchmarking each script.
+    /// It is not timed, and the variables it declares are visible to the script.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setup: Option<String>,
+    /// SQF run after benchmarking each script.
+    /// It is not timed, and sees the variables declared by `setup`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teardown: Option<String>,
+    /// The most times to run each script in a repetition.
+    /// `None` runs it up to 10000 times, either way it stops after a second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u32>,
+    /// Passed to each script as `_this`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Value>,
+    /// How each repetition is timed.
+    /// `None` uses [`Timing::CodePerformance`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+}
+
+impl Options {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How the server times a script, see [`Options::timing`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Timing {
+    /// With `diag_codePerformance`.
+    #[default]
+    CodePerformance,
+    /// By calling the script in a loop between readings of `diag_tickTime`,
+    /// for scripts `diag_codePerformance` can not run.
+    /// Less precise, and the time includes the loop.
+    TickTime,
+}
+
+impl Timing {
+    // serde's skip_serializing_if passes a reference.
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CompareRequest {
     pub id: u16,
@@ -132,30 +287,310 @@ pub struct CompareRequest {
 
 impl Message for CompareRequest {}
 
+/// Values for the local variables of a [`MatrixRequest`], by name such as `_size`.
+pub type Parameters = BTreeMap<String, Value>;
+
+/// Local variables the benchmark itself declares, which parameters can not shadow.
+pub const RESERVED_PARAMETERS: [&str; 11] = [
+    "_code",
+    "_compile",
+    "_cycles",
+    "_i",
+    "_out",
+    "_repetitions",
+    "_ret",
+    "_run",
+    "_samples",
+    "_start",
+    "_x",
+];
+
+/// Why `sets` of parameters can not be benchmarked, if they can't.
+#[must_use]
+pub fn invalid_parameters(sets: &[Parameters]) -> Option<String> {
+    // Names are written into the script as they are.
+    sets.iter()
+        .flat_map(BTreeMap::keys)
+        .find(|name| {
+            !(name.len() > 1
+                && name.starts_with('_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        })
+        .map(|name| format!("invalid parameter name {name:?}"))
+        .or_else(|| {
+            sets.iter()
+                .flat_map(BTreeMap::keys)
+                .find(|name| RESERVED_PARAMETERS.contains(&name.to_lowercase().as_str()))
+                .map(|name| format!("parameter name {name} is used by the benchmark"))
+        })
+        .or_else(|| {
+            sets.iter()
+                .flatten()
+                .find(|(_, value)| to_sqf(value).is_none())
+                .map(|(name, _)| format!("parameter {name} is not a finite number"))
+        })
+}
+
+/// Benchmark one script with each set of parameters, in one run of Arma.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MatrixRequest {
+    /// The script, which sees the parameters as local variables, as does the setup.
+    pub template: String,
+    pub parameters: Vec<Parameters>,
+}
+
+/// The result of one set of parameters in a [`MatrixRequest`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MatrixResult {
+    pub parameters: Parameters,
+    pub result: ExecuteResult,
+}
+
+impl Message for MatrixResult {}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CompareResult {
     pub id: u16,
     pub time: f64,
     pub iter: u32,
     pub ret: Value,
+    /// What Arma logged while benchmarking this script.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
+    /// Errors Arma reported while running this script.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ScriptError>,
+    /// The time of every repetition.
+    /// `time` and `iter` are from the first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<f64>,
+    /// Where the result differs from the baseline's when [`Options::assert_equal`] is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mismatches: Vec<Mismatch>,
+    /// How the script was timed.
+    #[serde(default, skip_serializing_if = "Timing::is_default")]
+    pub timing: Timing,
 }
 
 impl Message for CompareResult {}
 
+impl CompareResult {
+    /// Summarise the time of every repetition, see [`Statistics`].
+    #[must_use]
+    pub fn statistics(&self) -> Statistics {
+        Statistics::from_result(self.time, &self.samples)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ExecuteResult {
     pub time: f64,
     pub iter: u32,
     pub ret: Value,
+    /// What Arma logged while running the benchmark.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
+    /// Errors Arma reported while running the benchmark.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ScriptError>,
+    /// The time of every repetition.
+    /// `time` and `iter` are from the first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<f64>,
+    /// How the benchmark was timed.
+    #[serde(default, skip_serializing_if = "Timing::is_default")]
+    pub timing: Timing,
 }
 
 impl Message for ExecuteResult {}
 
+impl ExecuteResult {
+    /// Summarise the time of every repetition, see [`Statistics`].
+    #[must_use]
+    pub fn statistics(&self) -> Statistics {
+        Statistics::from_result(self.time, &self.samples)
+    }
+}
+
+/// An error Arma reported while compiling or running a script.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ScriptError {
+    /// The script, as named in the built request, such as `bench.sqf` or `0.sqf`.
+    pub file: String,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.file, self.message),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// Where a request is, sent while waiting for its [`Response`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Progress {
+    /// Waiting for a worker, with `position` requests ahead of it.
+    Queued { position: usize },
+    /// Installing or updating the Arma server.
+    Installing,
+    /// Starting Arma.
+    Starting,
+    /// Arma is running the benchmark.
+    Running,
+    /// Arma has exited, the result is being read.
+    Finished,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Response {
     Error(String),
     Execute(Result<ExecuteResult, String>),
     Compare(Result<Vec<CompareResult>, String>),
+    /// Sent before the final response when [`CAPABILITY_PROGRESS`] was negotiated.
+    Progress(Progress),
+    /// The request was cancelled with [`Command::Cancel`].
+    Cancelled,
+    /// Arma exited without a result, `exit_code` is `None` if it was killed by `signal`.
+    Crashed {
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+        logs: Vec<String>,
+    },
+    /// Arma exited, or hung, before the benchmark started.
+    NeverStarted {
+        logs: Vec<String>,
+    },
+    /// Arma ran the benchmark but exited without writing a result.
+    ResultMissing {
+        logs: Vec<String>,
+    },
+    /// The benchmark ran for longer than its timeout of `seconds` and was stopped.
+    TimedOut {
+        seconds: u64,
+        logs: Vec<String>,
+    },
+    /// A script failed to compile, so nothing was benchmarked.
+    CompileFailed {
+        file: String,
+        errors: Vec<ScriptError>,
+        logs: Vec<String>,
+    },
+    /// The results of a [`Request::Matrix`], in the order of its parameters.
+    Matrix(Result<Vec<MatrixResult>, String>),
+    /// The answer to a [`Command::History`], oldest first.
+    History(Result<Vec<HistoryRecord>, String>),
 }
 
 impl Message for Response {}
+
+impl Response {
+    /// Whether this is the last response for its request.
+    #[must_use]
+    pub const fn is_final(&self) -> bool {
+        !matches!(self, Self::Progress(_))
+    }
+
+    /// The result of an [`Request::Execute`].
+    ///
+    /// # Errors
+    /// Returns an error if the server reported one, or the response is not for an execute.
+    pub fn into_execute(self) -> Result<ExecuteResult, Error> {
+        match self {
+            Self::Execute(Ok(res)) => Ok(res),
+            Self::Execute(Err(err)) => Err(Error::ServerError(err)),
+            Self::Compare(_) | Self::Matrix(_) | Self::History(_) | Self::Progress(_) => {
+                Err(Error::UnexpectedResponse)
+            }
+            failure => Err(failure.into_error()),
+        }
+    }
+
+    /// The results of a [`Request::Compare`].
+    ///
+    /// # Errors
+    /// Returns an error if the server reported one, or the response is not for a compare.
+    pub fn into_compare(self) -> Result<Vec<CompareResult>, Error> {
+        match self {
+            Self::Compare(Ok(res)) => Ok(res),
+            Self::Compare(Err(err)) => Err(Error::ServerError(err)),
+            Self::Execute(_) | Self::Matrix(_) | Self::History(_) | Self::Progress(_) => {
+                Err(Error::UnexpectedResponse)
+            }
+            failure => Err(failure.into_error()),
+        }
+    }
+
+    /// The results of a [`Request::Matrix`].
+    ///
+    /// # Errors
+    /// Returns an error if the server reported one, or the response is not for a matrix.
+    pub fn into_matrix(self) -> Result<Vec<MatrixResult>, Error> {
+        match self {
+            Self::Matrix(Ok(res)) => Ok(res),
+            Self::Matrix(Err(err)) => Err(Error::ServerError(err)),
+            Self::Execute(_) | Self::Compare(_) | Self::History(_) | Self::Progress(_) => {
+                Err(Error::UnexpectedResponse)
+            }
+            failure => Err(failure.into_error()),
+        }
+    }
+
+    /// The records of a [`Command::History`].
+    ///
+    /// # Errors
+    /// Returns an error if the server reported one, or the response is not for a history query.
+    pub fn into_history(self) -> Result<Vec<HistoryRecord>, Error> {
+        match self {
+            Self::History(Ok(records)) => Ok(records),
+            Self::History(Err(err)) => Err(Error::ServerError(err)),
+            Self::Execute(_) | Self::Compare(_) | Self::Matrix(_) | Self::Progress(_) => {
+                Err(Error::UnexpectedResponse)
+            }
+            failure => Err(failure.into_error()),
+        }
+    }
+
+    /// The error for a response that is not a result.
+    #[must_use]
+    pub fn into_error(self) -> Error {
+        match self {
+            Self::Error(err) => Error::ServerError(err),
+            Self::Cancelled => Error::Cancelled,
+            Self::Crashed {
+                exit_code,
+                signal,
+                logs,
+            } => Error::Crashed {
+                exit_code,
+                signal,
+                logs,
+            },
+            Self::NeverStarted { logs } => Error::NeverStarted { logs },
+            Self::ResultMissing { logs } => Error::ResultMissing { logs },
+            Self::TimedOut { seconds, logs } => Error::RunTimedOut { seconds, logs },
+            Self::CompileFailed { file, errors, logs } => {
+                Error::CompileFailed { file, errors, logs }
+            }
+            Self::Execute(_)
+            | Self::Compare(_)
+            | Self::Matrix(_)
+            | Self::History(_)
+            | Self::Progress(_) => Error::UnexpectedResponse,
+        }
+    }
+}
+
+/// Sent by the server, tagged with the request it answers
+/// so several requests can be in flight on one connection.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Reply {
+    /// `None` for errors that can not be tied to a request, such as a command that failed to decode.
+    pub id: Option<RequestId>,
+    pub response: Response,
+}
+
+impl Message for Reply {}
@@ -0,0 +1,444 @@
+use std::io::Write;
+
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, Event},
+    Writer,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    to_sqf, Change, ChangeStatus, CompareResult, Comparison, ExecuteResult, Statistics, Verdict,
+};
+
+/// Results ready to share, in any [`ReportFormat`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Report {
+    pub groups: Vec<Group>,
+}
+
+/// Scripts that were benchmarked together, such as one comparison.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Group {
+    pub name: String,
+    /// The branch the scripts ran on, which keys them in a [`Baseline`](crate::Baseline).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Fastest first when compared.
+    pub entries: Vec<Entry>,
+    /// Why there are no entries, such as the scripts failing to compile.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+}
+
+/// One script in a [`Group`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub statistics: Statistics,
+    pub iterations: u32,
+    /// What the script returned, as SQF.
+    pub result: String,
+    /// How many times faster than the baseline, `None` when not compared.
+    pub speedup: Option<f64>,
+    pub verdict: Option<Verdict>,
+    pub p_value: Option<f64>,
+    /// Why the script should fail a CI run, such as script errors.
+    pub failures: Vec<String>,
+    /// How it compares to a saved run, see [`Baseline::compare`](crate::Baseline::compare).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<Change>,
+}
+
+/// Limits that fail an [`Entry`], see [`Report::check`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct Thresholds {
+    /// The slowest mean time per iteration allowed, in milliseconds.
+    pub max_mean: Option<f64>,
+    /// The lowest speedup over the baseline allowed, such as 0.9.
+    pub min_speedup: Option<f64>,
+}
+
+/// How [`Report::write`] renders a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ReportFormat {
+    /// Pretty printed.
+    Json,
+    /// One row per script.
+    Csv,
+    /// A GitHub flavored table per group, for pull request comments.
+    Markdown,
+    /// A test case per script, failing with its failures.
+    Junit,
+}
+
+impl Group {
+    /// A group of the one script `name`.
+    #[must_use]
+    pub fn execute(name: &str, result: &ExecuteResult) -> Self {
+        let errors = result.errors.iter().map(ToString::to_string).collect();
+        Self {
+            name: name.to_string(),
+            branch: None,
+            entries: vec![Entry {
+                name: name.to_string(),
+                statistics: result.statistics(),
+                iterations: result.iter,
+                result: sqf(&result.ret),
+                speedup: None,
+                verdict: None,
+                p_value: None,
+                failures: errors,
+                change: None,
+            }],
+            failures: Vec::new(),
+        }
+    }
+
+    /// How many entries failed, and the group itself if it has no results.
+    fn failures_count(&self) -> usize {
+        usize::from(!self.failures.is_empty())
+            + self
+                .entries
+                .iter()
+                .filter(|entry| !entry.failures.is_empty())
+                .count()
+    }
+
+    /// A group that has no results, failing with `failures`.
+    #[must_use]
+    pub fn failed(name: &str, failures: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            branch: None,
+            entries: Vec::new(),
+            failures,
+        }
+    }
+
+    /// The scripts of a comparison against `baseline`, with `scripts` giving their names by ID,
+    /// or `None` if the baseline has no result.
+    #[must_use]
+    pub fn compare(
+        name: &str,
+        scripts: &[String],
+        results: &[CompareResult],
+        baseline: u16,
+    ) -> Option<Self> {
+        let comparison = Comparison::new(results, baseline)?;
+        let entries = comparison
+            .ranking
+            .into_iter()
+            .filter_map(|ranked| {
+                let result = results.iter().find(|result| result.id == ranked.id)?;
+                let failures = result
+                    .errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .chain(
+                        result
+                            .mismatches
+                            .iter()
+                            .map(|mismatch| format!("result differs, {mismatch}")),
+                    )
+                    .collect();
+                Some(Entry {
+                    name: scripts
+                        .get(usize::from(ranked.id))
+                        .map_or_else(|| ranked.id.to_string(), Clone::clone),
+                    statistics: ranked.statistics,
+                    iterations: result.iter,
+                    result: sqf(&result.ret),
+                    speedup: Some(ranked.speedup),
+                    verdict: Some(ranked.verdict),
+                    p_value: ranked.p_value,
+                    failures,
+                    change: None,
+                })
+            })
+            .collect();
+        Some(Self {
+            name: name.to_string(),
+            branch: None,
+            entries,
+            failures: Vec::new(),
+        })
+    }
+}
+
+impl Report {
+    /// Fail every entry outside `thresholds`.
+    pub fn check(&mut self, thresholds: &Thresholds) {
+        for entry in self.groups.iter_mut().flat_map(|group| &mut group.entries) {
+            if let Some(max) = thresholds.max_mean {
+                if entry.statistics.mean > max {
+                    entry.failures.push(format!(
+                        "mean of {:.6} ms is above the limit of {max} ms",
+                        entry.statistics.mean
+                    ));
+                }
+            }
+            if let (Some(min), Some(speedup)) = (thresholds.min_speedup, entry.speedup) {
+                if speedup < min {
+                    entry.failures.push(format!(
+                        "speedup of {speedup:.2}x is below the limit of {min}x"
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Whether any group or entry failed.
+    #[must_use]
+    pub fn failed(&self) -> bool {
+        self.failures() > 0
+    }
+
+    /// How many groups and entries failed.
+    #[must_use]
+    pub fn failures(&self) -> usize {
+        self.groups.iter().map(Group::failures_count).sum()
+    }
+
+    /// Render the report as `format`.
+    ///
+    /// # Errors
+    /// Returns an error if `out` can not be written to.
+    pub fn write(&self, format: ReportFormat, out: impl Write) -> std::io::Result<()> {
+        match format {
+            ReportFormat::Json => self.write_json(out),
+            ReportFormat::Csv => self.write_csv(out),
+            ReportFormat::Markdown => self.write_markdown(out),
+            ReportFormat::Junit => self.write_junit(out),
+        }
+    }
+
+    /// # Errors
+    /// Returns an error if `out` can not be written to.
+    pub fn write_json(&self, mut out: impl Write) -> std::io::Result<()> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)
+    }
+
+    /// # Errors
+    /// Returns an error if `out` can not be written to.
+    pub fn write_csv(&self, out: impl Write) -> std::io::Result<()> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record([
+            "group",
+            "script",
+            "samples",
+            "mean_ms",
+            "median_ms",
+            "stddev_ms",
+            "min_ms",
+            "max_ms",
+            "iterations",
+            "speedup",
+            "verdict",
+            "p_value",
+            "result",
+            "failures",
+            "branch",
+            "baseline_ms",
+            "change",
+        ])?;
+        for group in &self.groups {
+            if !group.failures.is_empty() {
+                // A row of its own, with no script or numbers.
+                let none = String::new;
+                writer.write_record([
+                    group.name.clone(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    none(),
+                    group.failures.join("; "),
+                    group.branch.clone().unwrap_or_default(),
+                    none(),
+                    none(),
+                ])?;
+            }
+            for entry in &group.entries {
+                let statistics = &entry.statistics;
+                writer.write_record([
+                    group.name.clone(),
+                    entry.name.clone(),
+                    statistics.samples.len().to_string(),
+                    statistics.mean.to_string(),
+                    statistics.median.to_string(),
+                    statistics.stddev.to_string(),
+                    statistics.min.to_string(),
+                    statistics.max.to_string(),
+                    entry.iterations.to_string(),
+                    optional(entry.speedup),
+                    entry
+                        .verdict
+                        .map(|verdict| verdict.to_string())
+                        .unwrap_or_default(),
+                    optional(entry.p_value),
+                    entry.result.clone(),
+                    entry.failures.join("; "),
+                    group.branch.clone().unwrap_or_default(),
+                    optional(entry.change.map(|change| change.baseline)),
+                    entry
+                        .change
+                        .map(|change| change.status.to_string())
+                        .unwrap_or_default(),
+                ])?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// # Errors
+    /// Returns an error if `out` can not be written to.
+    pub fn write_markdown(&self, mut out: impl Write) -> std::io::Result<()> {
+        for (index, group) in self.groups.iter().enumerate() {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "### {}", markdown(&group.name))?;
+            writeln!(out)?;
+            if !group.failures.is_empty() {
+                writeln!(out, "❌ {}", markdown(&group.failures.join("; ")))?;
+            }
+            if group.entries.is_empty() {
+                continue;
+            }
+            if !group.failures.is_empty() {
+                writeln!(out)?;
+            }
+            writeln!(
+                out,
+                "| Script | Mean (ms) | ± (ms) | Speedup | Verdict | Result | Status |"
+            )?;
+            writeln!(out, "| --- | ---: | ---: | ---: | --- | --- | --- |")?;
+            for entry in &group.entries {
+                // Regressions are failures, improvements are worth pointing out too.
+                let status = if entry.failures.is_empty() {
+                    entry
+                        .change
+                        .filter(|change| change.status == ChangeStatus::Improvement)
+                        .map_or_else(|| "✅".to_string(), |change| format!("✅ {change}"))
+                } else {
+                    format!("❌ {}", markdown(&entry.failures.join("; ")))
+                };
+                writeln!(
+                    out,
+                    "| `{}` | {:.6} | {:.6} | {} | {} | `{}` | {status} |",
+                    markdown(&entry.name.replace('`', "'")),
+                    entry.statistics.mean,
+                    entry.statistics.stddev,
+                    entry
+                        .speedup
+                        .map(|speedup| format!("{speedup:.2}x"))
+                        .unwrap_or_default(),
+                    entry
+                        .verdict
+                        .map(|verdict| verdict.to_string())
+                        .unwrap_or_default(),
+                    markdown(&entry.result.replace('`', "'")),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Each group is a test suite, timed by the mean of each script.
+    ///
+    /// # Errors
+    /// Returns an error if `out` can not be written to.
+    pub fn write_junit(&self, out: impl Write) -> std::io::Result<()> {
+        // A group that failed without results is a test case of its own.
+        let tests = |group: &Group| group.entries.len() + usize::from(!group.failures.is_empty());
+        let mut writer = Writer::new_with_indent(out, b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        writer.write_event(Event::Start(
+            BytesStart::new("testsuites").with_attributes([
+                ("name", "arma-bench"),
+                (
+                    "tests",
+                    self.groups
+                        .iter()
+                        .map(tests)
+                        .sum::<usize>()
+                        .to_string()
+                        .as_str(),
+                ),
+                ("failures", self.failures().to_string().as_str()),
+            ]),
+        ))?;
+        for group in &self.groups {
+            writer.write_event(Event::Start(BytesStart::new("testsuite").with_attributes(
+                [
+                    ("name", group.name.as_str()),
+                    ("tests", tests(group).to_string().as_str()),
+                    ("failures", group.failures_count().to_string().as_str()),
+                ],
+            )))?;
+            if !group.failures.is_empty() {
+                let case = BytesStart::new("testcase").with_attributes([
+                    ("name", group.name.as_str()),
+                    ("classname", group.name.as_str()),
+                ]);
+                write_case(&mut writer, case, &group.failures)?;
+            }
+            for entry in &group.entries {
+                let case = BytesStart::new("testcase").with_attributes([
+                    ("name", entry.name.as_str()),
+                    ("classname", group.name.as_str()),
+                    // JUnit times are in seconds.
+                    (
+                        "time",
+                        (entry.statistics.mean / 1000.0).to_string().as_str(),
+                    ),
+                ]);
+                write_case(&mut writer, case, &entry.failures)?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("testsuite")))?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("testsuites")))?;
+        writer.into_inner().write_all(b"\n")
+    }
+}
+
+/// A test case, failing with each of `failures`.
+fn write_case(
+    writer: &mut Writer<impl Write>,
+    case: BytesStart<'_>,
+    failures: &[String],
+) -> std::io::Result<()> {
+    if failures.is_empty() {
+        return writer.write_event(Event::Empty(case));
+    }
+    writer.write_event(Event::Start(case))?;
+    for failure in failures {
+        writer.write_event(Event::Empty(
+            BytesStart::new("failure").with_attributes([("message", failure.as_str())]),
+        ))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("testcase")))
+}
+
+fn sqf(value: &arma_rs::Value) -> String {
+    to_sqf(value).unwrap_or_else(|| value.to_string())
+}
+
+fn optional(number: Option<f64>) -> String {
+    number.map(|number| number.to_string()).unwrap_or_default()
+}
+
+/// `text` that can go in a table cell.
+fn markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+        .replace("\r\n", " ")
+        .replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,22 @@
+use arma_rs::Value;
+
+/// `value` written as SQF, or `None` for numbers SQF has no literal for.
+#[must_use]
+pub fn to_sqf(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Null => "nil".to_string(),
+        Value::Number(number) if number.is_finite() => number.to_string(),
+        Value::Number(_) => return None,
+        Value::Boolean(boolean) => boolean.to_string(),
+        Value::String(string) => format!("\"{}\"", string.replace('"', "\"\"")),
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(to_sqf)
+                .collect::<Option<Vec<_>>>()?
+                .join(", ")
+        ),
+        Value::Unknown(code) => code.clone(),
+    })
+}
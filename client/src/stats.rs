@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+
+/// A summary of repeated benchmark times, in milliseconds per iteration.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Statistics {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    /// The sample standard deviation, 0 for a single sample.
+    pub stddev: f64,
+    /// Every sample, in the order they were taken.
+    pub samples: Vec<f64>,
+}
+
+impl Statistics {
+    /// Summarise `samples`, or `None` if there are none.
+    #[must_use]
+    pub fn new(samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let sorted = sorted(&samples);
+        let mean = samples.iter().sum::<f64>() / count(&samples);
+        let stddev = if samples.len() < 2 {
+            0.0
+        } else {
+            let squares = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>();
+            (squares / (count(&samples) - 1.0)).sqrt()
+        };
+        Some(Self {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean,
+            median: percentile(&sorted, 50.0),
+            stddev,
+            samples,
+        })
+    }
+
+    /// Summarise a result's samples, or its one `time` when it has none.
+    #[must_use]
+    pub fn from_result(time: f64, samples: &[f64]) -> Self {
+        Self::new(samples.to_vec()).unwrap_or_else(|| Self {
+            min: time,
+            max: time,
+            mean: time,
+            median: time,
+            stddev: 0.0,
+            samples: vec![time],
+        })
+    }
+
+    /// The time `p` percent of samples are at or below, between 0 and 100,
+    /// interpolating between the closest samples.
+    #[must_use]
+    pub fn percentile(&self, p: f64) -> f64 {
+        percentile(&sorted(&self.samples), p)
+    }
+
+    /// The range the true mean is in with `confidence`, such as 0.95,
+    /// or just the mean with a single sample.
+    #[must_use]
+    pub fn interval(&self, confidence: f64) -> Interval {
+        if self.samples.len() < 2 {
+            return Interval {
+                low: self.mean,
+                high: self.mean,
+            };
+        }
+        let df = count(&self.samples) - 1.0;
+        let margin = t_quantile(confidence, df) * self.stddev / count(&self.samples).sqrt();
+        Interval {
+            low: self.mean - margin,
+            high: self.mean + margin,
+        }
+    }
+}
+
+/// A confidence interval, see [`Statistics::interval`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Interval {
+    pub low: f64,
+    pub high: f64,
+}
+
+fn sorted(samples: &[f64]) -> Vec<f64> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+#[allow(clippy::cast_precision_loss)]
+const fn count(samples: &[f64]) -> f64 {
+    samples.len() as f64
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = p.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    (sorted[upper] - sorted[lower]).mul_add(rank - rank.floor(), sorted[lower])
+}
+
+/// The interval of `other`'s mean minus `base`'s, and the p-value of Welch's t-test.
+pub fn welch(base: &Statistics, other: &Statistics, confidence: f64) -> (Interval, Option<f64>) {
+    let difference = other.mean - base.mean;
+    if base.samples.len() < 2 || other.samples.len() < 2 {
+        let point = Interval {
+            low: difference,
+            high: difference,
+        };
+        return (point, None);
+    }
+    let (base_variance, other_variance) = (mean_variance(base), mean_variance(other));
+    let variance = base_variance + other_variance;
+    if variance == 0.0 {
+        // Neither varied at all, any difference is real.
+        let point = Interval {
+            low: difference,
+            high: difference,
+        };
+        return (point, Some(if difference == 0.0 { 1.0 } else { 0.0 }));
+    }
+    let error = variance.sqrt();
+    // The Welch-Satterthwaite approximation of the degrees of freedom.
+    let df = variance.powi(2)
+        / (base_variance.powi(2) / (count(&base.samples) - 1.0)
+            + other_variance.powi(2) / (count(&other.samples) - 1.0));
+    let margin = t_quantile(confidence, df) * error;
+    let interval = Interval {
+        low: difference - margin,
+        high: difference + margin,
+    };
+    (interval, Some(t_p_value(difference / error, df)))
+}
+
+/// The variance of the mean, the squared standard error.
+fn mean_variance(statistics: &Statistics) -> f64 {
+    statistics.stddev.powi(2) / count(&statistics.samples)
+}
+
+/// The chance of a Student's t statistic at least as far from 0 as `t`, in either direction.
+fn t_p_value(t: f64, df: f64) -> f64 {
+    if t.is_infinite() {
+        return 0.0;
+    }
+    incomplete_beta(df / 2.0, 0.5, df / t.mul_add(t, df))
+}
+
+/// The `t` that `confidence` of a Student's t distribution is within, either side of 0.
+fn t_quantile(confidence: f64, df: f64) -> f64 {
+    let alpha = 1.0 - confidence;
+    let (mut low, mut high) = (0.0, 1e6);
+    for _ in 0..200 {
+        let mid = f64::midpoint(low, high);
+        if t_p_value(mid, df) > alpha {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    f64::midpoint(low, high)
+}
+
+/// The regularized incomplete beta function, from Numerical Recipes.
+#[allow(clippy::many_single_char_names)]
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let log = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b);
+    let front = b.mul_add((1.0 - x).ln(), a.mul_add(x.ln(), log)).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// The continued fraction for [`incomplete_beta`], evaluated with Lentz's method.
+#[allow(clippy::many_single_char_names)]
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = f64::from(m);
+        let m2 = 2.0 * m;
+        for numerator in [
+            m * (b - m) * x / ((a + m2 - 1.0) * (a + m2)),
+            -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0)),
+        ] {
+            d = numerator.mul_add(d, 1.0);
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    h
+}
+
+/// The natural log of the gamma function, using the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let tmp = x + 5.5;
+    let tmp = (x + 0.5).mul_add(tmp.ln(), -tmp);
+    let mut series = 1.000_000_000_190_015;
+    let mut y = x;
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+    tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
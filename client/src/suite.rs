@@ -0,0 +1,592 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use arma_rs::Value;
+use serde::Deserialize;
+
+use crate::{
+    invalid_parameters, mismatches, to_sqf, AssertEqual, Client, CompareRequest, CompareResult,
+    Error, ExecuteResult, Group, MatrixRequest, Options, Parameters, Pending, Report, Request,
+    ServerConfig, Thresholds,
+};
+
+/// Benchmarks read from a TOML manifest, see [`Suite::load`].
+///
+/// ```toml
+/// branch = "profiling"
+/// repetitions = 10
+///
+/// [thresholds]
+/// min_speedup = 0.9
+///
+/// [[benchmark]]
+/// name = "count"
+/// setup = "private _array = []; _array resize _size;"
+/// parameters = [{ _size = 10 }, { _size = 1000 }]
+/// expect = 1000
+///
+/// [[benchmark.variant]]
+/// name = "count"
+/// code = "count _array"
+///
+/// [[benchmark.variant]]
+/// name = "forEach"
+/// file = "count_foreach.sqf"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suite {
+    pub name: Option<String>,
+    /// Overrides the branch of the [`ServerConfig`] given to [`Suite::run`].
+    pub branch: Option<String>,
+    /// Overrides the binary of the [`ServerConfig`] given to [`Suite::run`].
+    pub binary: Option<String>,
+    pub repetitions: Option<u32>,
+    pub boots: Option<u32>,
+    pub timeout: Option<u64>,
+    /// SQF run before the setup of every benchmark.
+    pub setup: Option<String>,
+    pub thresholds: Thresholds,
+    pub benchmarks: Vec<Benchmark>,
+}
+
+/// Scripts that do the same thing, compared against each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Benchmark {
+    pub name: String,
+    /// Overrides the branch of the suite.
+    pub branch: Option<String>,
+    pub setup: Option<String>,
+    pub teardown: Option<String>,
+    /// Each set is declared as local variables before the setup, and compared on its own.
+    pub parameters: Vec<Parameters>,
+    /// The index of the variant the others are compared against.
+    pub baseline: u16,
+    /// What every variant must return.
+    pub expect: Option<Value>,
+    /// Whether every variant must return the same as the baseline.
+    pub assert_equal: bool,
+    /// How far apart numbers may be and still be equal, for `expect` and `assert_equal`.
+    pub tolerance: f64,
+    pub variants: Vec<Variant>,
+}
+
+/// One way of doing what a [`Benchmark`] does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: String,
+    pub sqfc: bool,
+    pub content: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    name: Option<String>,
+    branch: Option<String>,
+    binary: Option<String>,
+    repetitions: Option<u32>,
+    boots: Option<u32>,
+    timeout: Option<u64>,
+    setup: Option<String>,
+    setup_file: Option<PathBuf>,
+    #[serde(default)]
+    thresholds: Thresholds,
+    #[serde(default, rename = "benchmark")]
+    benchmarks: Vec<ManifestBenchmark>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestBenchmark {
+    name: String,
+    branch: Option<String>,
+    setup: Option<String>,
+    setup_file: Option<PathBuf>,
+    teardown: Option<String>,
+    teardown_file: Option<PathBuf>,
+    #[serde(default)]
+    parameters: Vec<toml::Table>,
+    baseline: Option<String>,
+    expect: Option<toml::Value>,
+    #[serde(default)]
+    assert_equal: bool,
+    #[serde(default)]
+    tolerance: f64,
+    #[serde(default, rename = "variant")]
+    variants: Vec<ManifestVariant>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestVariant {
+    name: Option<String>,
+    code: Option<String>,
+    file: Option<PathBuf>,
+}
+
+impl Suite {
+    /// Read the manifest at `path`, and the scripts it names relative to it.
+    ///
+    /// # Errors
+    /// Returns an error if a file can not be read or the manifest is invalid.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let manifest = std::fs::read_to_string(path)
+            .map_err(|e| Error::InvalidSuite(format!("failed to read {}: {e}", path.display())))?;
+        Self::parse(&manifest, path.parent().unwrap_or_else(|| Path::new(".")))
+    }
+
+    /// Parse a manifest, reading the scripts it names relative to `dir`.
+    ///
+    /// # Errors
+    /// Returns an error if a file can not be read or the manifest is invalid.
+    pub fn parse(manifest: &str, dir: &Path) -> Result<Self, Error> {
+        let manifest: Manifest =
+            toml::from_str(manifest).map_err(|e| Error::InvalidSuite(e.to_string()))?;
+        if manifest.benchmarks.is_empty() {
+            return Err(Error::InvalidSuite("there are no benchmarks".to_string()));
+        }
+        Ok(Self {
+            name: manifest.name,
+            branch: manifest.branch,
+            binary: manifest.binary,
+            repetitions: manifest.repetitions,
+            boots: manifest.boots,
+            timeout: manifest.timeout,
+            setup: source(dir, "setup", manifest.setup, manifest.setup_file)?,
+            thresholds: manifest.thresholds,
+            benchmarks: manifest
+                .benchmarks
+                .into_iter()
+                .map(|benchmark| Benchmark::parse(benchmark, dir))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Run every benchmark, connecting to each branch they need,
+    /// with the suite's settings overriding `config` and `options`.
+    ///
+    /// Every request is submitted before waiting for any, so servers with several workers
+    /// run them side by side. A benchmark that fails is reported as a failed [`Group`],
+    /// so the rest still run.
+    ///
+    /// # Errors
+    /// Returns an error if a connection fails.
+    pub fn run(
+        &self,
+        host: &str,
+        port: u16,
+        config: &ServerConfig,
+        options: &Options,
+    ) -> Result<Report, Error> {
+        let mut clients: HashMap<ServerConfig, Client> = HashMap::new();
+        let mut submitted = Vec::new();
+        for benchmark in &self.benchmarks {
+            let config = ServerConfig {
+                binary: self.binary.clone().unwrap_or_else(|| config.binary.clone()),
+                branch: benchmark
+                    .branch
+                    .clone()
+                    .or_else(|| self.branch.clone())
+                    .unwrap_or_else(|| config.branch.clone()),
+                branch_password: config.branch_password.clone(),
+            };
+            let branch = config.branch.clone();
+            let client = match clients.entry(config) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    let client = Client::connect_with_port(host, port, entry.key())?;
+                    entry.insert(client)
+                }
+            };
+            for run in self.submit(client, benchmark, options) {
+                submitted.push((benchmark, branch.clone(), run));
+            }
+        }
+        let mut report = Report {
+            groups: submitted
+                .into_iter()
+                .flat_map(|(benchmark, branch, run)| {
+                    benchmark.groups(run).into_iter().map(move |group| Group {
+                        branch: Some(branch.clone()),
+                        ..group
+                    })
+                })
+                .collect(),
+        };
+        report.check(&self.thresholds);
+        Ok(report)
+    }
+
+    /// Submit the requests of `benchmark`.
+    ///
+    /// With parameters, each variant runs every set in one start of Arma.
+    /// Compiled variants can not be templates, so those are compared a set at a time instead.
+    fn submit(&self, client: &Client, benchmark: &Benchmark, options: &Options) -> Vec<Run> {
+        // A benchmark built without the manifest may have parameters that were never checked.
+        if let Some(message) = invalid_parameters(&benchmark.parameters) {
+            return vec![Run::Compare {
+                name: benchmark.name.clone(),
+                pending: Err(Error::InvalidSuite(format!(
+                    "benchmark {}: {message}",
+                    benchmark.name
+                ))),
+            }];
+        }
+        let options_for = |parameters: &Parameters, assert_equal: bool| {
+            let mut setup = String::new();
+            for (name, value) in parameters {
+                let value = to_sqf(value).unwrap_or_default();
+                let _ = writeln!(setup, "private {name} = {value};");
+            }
+            for part in [&self.setup, &benchmark.setup].into_iter().flatten() {
+                setup.push_str(part);
+                setup.push_str("\n;\n");
+            }
+            Options {
+                timeout: self.timeout.or(options.timeout),
+                repetitions: self.repetitions.or(options.repetitions),
+                boots: self.boots.or(options.boots),
+                assert_equal: (assert_equal && benchmark.assert_equal).then_some(AssertEqual {
+                    baseline: benchmark.baseline,
+                    tolerance: benchmark.tolerance,
+                }),
+                setup: (!setup.is_empty()).then_some(setup),
+                teardown: benchmark.teardown.clone(),
+                ..options.clone()
+            }
+        };
+        let templates = benchmark
+            .variants
+            .iter()
+            .map(|variant| {
+                String::from_utf8(variant.content.clone())
+                    .ok()
+                    .filter(|_| !variant.sqfc)
+            })
+            .collect::<Option<Vec<_>>>();
+        if let (false, Some(templates)) = (benchmark.parameters.is_empty(), templates) {
+            // Parameters are declared by the matrix, before the setup runs.
+            let options = options_for(&Parameters::new(), false);
+            return vec![Run::Matrix {
+                names: benchmark
+                    .parameters
+                    .iter()
+                    .map(|parameters| benchmark.name(parameters))
+                    .collect(),
+                pending: templates
+                    .into_iter()
+                    .map(|template| {
+                        client.submit_with_options(
+                            Request::Matrix(MatrixRequest {
+                                template,
+                                parameters: benchmark.parameters.clone(),
+                            }),
+                            options.clone(),
+                        )
+                    })
+                    .collect(),
+            }];
+        }
+        let sets = if benchmark.parameters.is_empty() {
+            vec![Parameters::new()]
+        } else {
+            benchmark.parameters.clone()
+        };
+        sets.iter()
+            .map(|parameters| {
+                let request = Request::Compare(
+                    (0..)
+                        .zip(&benchmark.variants)
+                        .map(|(id, variant)| CompareRequest {
+                            id,
+                            sqfc: variant.sqfc,
+                            content: variant.content.clone(),
+                        })
+                        .collect(),
+                );
+                Run::Compare {
+                    name: benchmark.name(parameters),
+                    pending: client.submit_with_options(request, options_for(parameters, true)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Requests submitted for a [`Benchmark`], see [`Suite::submit`].
+enum Run {
+    /// Every variant compared with one set of parameters.
+    Compare {
+        name: String,
+        pending: Result<Pending, Error>,
+    },
+    /// Each variant with every set of parameters, named by set, in the order of the variants.
+    Matrix {
+        names: Vec<String>,
+        pending: Vec<Result<Pending, Error>>,
+    },
+}
+
+impl Benchmark {
+    fn parse(manifest: ManifestBenchmark, dir: &Path) -> Result<Self, Error> {
+        let invalid = |message: String| {
+            Error::InvalidSuite(format!("benchmark {}: {message}", manifest.name))
+        };
+        if manifest.variants.is_empty() {
+            return Err(invalid("there are no variants".to_string()));
+        }
+        let variants = manifest
+            .variants
+            .into_iter()
+            .map(|variant| Variant::parse(variant, dir))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| invalid(e.to_string()))?;
+        if let Some(variant) = variants
+            .iter()
+            .enumerate()
+            .find(|(index, variant)| variants[..*index].iter().any(|v| v.name == variant.name))
+            .map(|(_, variant)| variant)
+        {
+            return Err(invalid(format!("variant {} is named twice", variant.name)));
+        }
+        let baseline = match &manifest.baseline {
+            Some(baseline) => variants
+                .iter()
+                .position(|variant| &variant.name == baseline)
+                .ok_or_else(|| {
+                    invalid(format!("baseline {baseline} is not one of the variants"))
+                })?,
+            None => 0,
+        };
+        let parameters = manifest
+            .parameters
+            .into_iter()
+            .map(|set| {
+                set.into_iter()
+                    .map(|(name, value)| {
+                        let value = value_from_toml(value)
+                            .ok_or_else(|| format!("parameter {name} can not be written in SQF"))?;
+                        Ok((name, value))
+                    })
+                    .collect::<Result<Parameters, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid)?;
+        // Checked as the server would, so a suite fails before anything runs.
+        if let Some(message) = invalid_parameters(&parameters) {
+            return Err(invalid(message));
+        }
+        let expect = manifest
+            .expect
+            .map(|value| {
+                value_from_toml(value)
+                    .ok_or_else(|| invalid("expect can not be written in SQF".to_string()))
+            })
+            .transpose()?;
+        Ok(Self {
+            setup: source(dir, "setup", manifest.setup, manifest.setup_file)
+                .map_err(|e| invalid(e.to_string()))?,
+            teardown: source(dir, "teardown", manifest.teardown, manifest.teardown_file)
+                .map_err(|e| invalid(e.to_string()))?,
+            baseline: u16::try_from(baseline).map_err(|_| invalid("too many variants".into()))?,
+            name: manifest.name,
+            branch: manifest.branch,
+            parameters,
+            expect,
+            assert_equal: manifest.assert_equal,
+            tolerance: manifest.tolerance,
+            variants,
+        })
+    }
+
+    /// What to call the results of `parameters`, such as `count (_size = 10)`.
+    fn name(&self, parameters: &Parameters) -> String {
+        if parameters.is_empty() {
+            return self.name.clone();
+        }
+        let values = parameters
+            .iter()
+            .map(|(name, value)| format!("{name} = {}", to_sqf(value).unwrap_or_default()))
+            .collect::<Vec<_>>();
+        format!("{} ({})", self.name, values.join(", "))
+    }
+
+    /// Wait for the results of a run, a group for each set of parameters.
+    fn groups(&self, run: Run) -> Vec<Group> {
+        match run {
+            Run::Compare { name, pending } => {
+                let results = pending
+                    .and_then(Pending::wait)
+                    .and_then(crate::Response::into_compare);
+                vec![match results {
+                    Ok(results) => self.group(&name, results),
+                    Err(e) => Group::failed(&name, failures(&e)),
+                }]
+            }
+            Run::Matrix { names, pending } => {
+                // Waited on in full, so one failed variant does not leave the others behind.
+                let variants = pending
+                    .into_iter()
+                    .map(|pending| {
+                        pending
+                            .and_then(Pending::wait)
+                            .and_then(crate::Response::into_matrix)
+                    })
+                    .collect::<Vec<_>>();
+                let variants = match variants.into_iter().collect::<Result<Vec<_>, _>>() {
+                    Ok(variants) => variants,
+                    Err(e) => {
+                        return names
+                            .iter()
+                            .map(|name| Group::failed(name, failures(&e)))
+                            .collect();
+                    }
+                };
+                names
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| {
+                        let results = (0..)
+                            .zip(&variants)
+                            .filter_map(|(id, results)| {
+                                results.get(index).map(|set| compared(id, &set.result))
+                            })
+                            .collect();
+                        self.group(name, self.assert_equal(results))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Record where each result differs from the baseline's, as the server does for a comparison.
+    fn assert_equal(&self, mut results: Vec<CompareResult>) -> Vec<CompareResult> {
+        if !self.assert_equal {
+            return results;
+        }
+        let Some(expected) = results
+            .iter()
+            .find(|result| result.id == self.baseline)
+            .map(|result| result.ret.clone())
+        else {
+            return results;
+        };
+        for result in &mut results {
+            result.mismatches = mismatches(&expected, &result.ret, self.tolerance);
+        }
+        results
+    }
+
+    /// Check results against `expect`, and rank them.
+    fn group(&self, name: &str, mut results: Vec<CompareResult>) -> Group {
+        if let Some(expect) = &self.expect {
+            for result in &mut results {
+                result
+                    .mismatches
+                    .extend(mismatches(expect, &result.ret, self.tolerance));
+            }
+        }
+        let variants = self
+            .variants
+            .iter()
+            .map(|variant| variant.name.clone())
+            .collect::<Vec<_>>();
+        Group::compare(name, &variants, &results, self.baseline)
+            .unwrap_or_else(|| Group::failed(name, vec![Error::UnexpectedResponse.to_string()]))
+    }
+}
+
+/// The result of one set of a matrix, as if the variant `id` had been compared.
+fn compared(id: u16, result: &ExecuteResult) -> CompareResult {
+    CompareResult {
+        id,
+        time: result.time,
+        iter: result.iter,
+        ret: result.ret.clone(),
+        logs: result.logs.clone(),
+        errors: result.errors.clone(),
+        samples: result.samples.clone(),
+        mismatches: Vec::new(),
+        timing: result.timing,
+    }
+}
+
+/// Why a request failed, with each error in the scripts that failed to compile.
+fn failures(error: &Error) -> Vec<String> {
+    let mut failures = vec![error.to_string()];
+    if let Error::CompileFailed { errors, .. } = error {
+        failures.extend(errors.iter().map(ToString::to_string));
+    }
+    failures
+}
+
+impl Variant {
+    fn parse(manifest: ManifestVariant, dir: &Path) -> Result<Self, Error> {
+        match (manifest.code, manifest.file) {
+            (Some(_), None) if manifest.name.is_none() => Err(Error::InvalidSuite(
+                "variants with code need a name".to_string(),
+            )),
+            (Some(code), None) => Ok(Self {
+                name: manifest.name.unwrap_or_default(),
+                sqfc: false,
+                content: code.into_bytes(),
+            }),
+            (None, Some(file)) => {
+                let path = dir.join(&file);
+                let content = std::fs::read(&path).map_err(|e| {
+                    Error::InvalidSuite(format!("failed to read {}: {e}", path.display()))
+                })?;
+                Ok(Self {
+                    name: manifest.name.unwrap_or_else(|| file.display().to_string()),
+                    sqfc: file.extension().is_some_and(|ext| ext == "sqfc"),
+                    content,
+                })
+            }
+            _ => Err(Error::InvalidSuite(
+                "each variant needs either code or a file".to_string(),
+            )),
+        }
+    }
+}
+
+/// SQF given inline or in a file, but not both.
+fn source(
+    dir: &Path,
+    what: &str,
+    code: Option<String>,
+    file: Option<PathBuf>,
+) -> Result<Option<String>, Error> {
+    match (code, file) {
+        (Some(_), Some(_)) => Err(Error::InvalidSuite(format!(
+            "{what} and {what}_file can not both be set"
+        ))),
+        (code, None) => Ok(code),
+        (None, Some(file)) => {
+            let path = dir.join(file);
+            std::fs::read_to_string(&path)
+                .map(Some)
+                .map_err(|e| Error::InvalidSuite(format!("failed to read {}: {e}", path.display())))
+        }
+    }
+}
+
+/// `value` as SQF sees it, or `None` for tables and dates.
+#[allow(clippy::cast_precision_loss)]
+fn value_from_toml(value: toml::Value) -> Option<Value> {
+    Some(match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::Number(integer as f64),
+        toml::Value::Float(float) => Value::Number(float),
+        toml::Value::Boolean(boolean) => Value::Boolean(boolean),
+        toml::Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(value_from_toml)
+                .collect::<Option<_>>()?,
+        ),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => return None,
+    })
+}
@@ -0,0 +1,123 @@
+use arma_bench::{Baseline, ChangeStatus, CompareResult, Error, Group, Report, Statistics, Timing};
+use arma_rs::Value;
+
+fn report(branch: &str, fast: f64, slow: f64) -> Report {
+    let result = |id, mean| CompareResult {
+        id,
+        time: mean,
+        iter: 10000,
+        ret: Value::Number(1.0),
+        logs: Vec::new(),
+        errors: Vec::new(),
+        samples: vec![mean, mean],
+        mismatches: Vec::new(),
+        timing: Timing::default(),
+    };
+    let names = ["fast.sqf".to_string(), "slow.sqf".to_string()];
+    let group = Group::compare("count", &names, &[result(0, fast), result(1, slow)], 0)
+        .expect("Missing baseline");
+    Report {
+        groups: vec![Group {
+            branch: Some(branch.to_string()),
+            ..group
+        }],
+    }
+}
+
+fn entry<'a>(report: &'a Report, name: &str) -> &'a arma_bench::Entry {
+    report.groups[0]
+        .entries
+        .iter()
+        .find(|entry| entry.name == name)
+        .expect("Missing entry")
+}
+
+#[test]
+fn record() {
+    let mut baseline = Baseline::default();
+    baseline.record(&report("public", 0.001, 0.002));
+    assert_eq!(
+        baseline.get("public", "count", "slow.sqf"),
+        Statistics::new(vec![0.002, 0.002]).as_ref()
+    );
+    assert_eq!(baseline.get("profiling", "count", "slow.sqf"), None);
+
+    baseline.record(&report("public", 0.001, 0.003));
+    assert_eq!(
+        baseline
+            .get("public", "count", "slow.sqf")
+            .map(|statistics| statistics.mean),
+        Some(0.003)
+    );
+}
+
+#[test]
+fn regressions_and_improvements() {
+    let mut baseline = Baseline::default();
+    baseline.record(&report("public", 0.002, 0.002));
+    let mut current = report("public", 0.00203, 0.003);
+    let new = arma_bench::Entry {
+        name: "new.sqf".to_string(),
+        ..entry(&current, "fast.sqf").clone()
+    };
+    current.groups[0].entries.push(new);
+    baseline.compare(&mut current, 0.05);
+
+    let fast = entry(&current, "fast.sqf");
+    let change = fast.change.expect("Missing change");
+    assert_eq!(change.status, ChangeStatus::Unchanged);
+    assert!(fast.failures.is_empty());
+
+    let slow = entry(&current, "slow.sqf");
+    let change = slow.change.expect("Missing change");
+    assert_eq!(change.status, ChangeStatus::Regression);
+    assert!((change.ratio - 1.5).abs() < 1e-9);
+    assert_eq!(change.to_string(), "+50.0% (regression)");
+    assert_eq!(
+        slow.failures,
+        ["mean of 0.003000 ms is 50.0% slower than the baseline of 0.002000 ms"]
+    );
+    assert!(current.failed());
+
+    assert_eq!(entry(&current, "new.sqf").change, None);
+
+    let mut faster = report("public", 0.001, 0.002);
+    baseline.compare(&mut faster, 0.05);
+    let fast = entry(&faster, "fast.sqf");
+    assert_eq!(
+        fast.change.map(|change| change.status),
+        Some(ChangeStatus::Improvement)
+    );
+    assert!(!faster.failed());
+}
+
+#[test]
+fn branches_are_separate() {
+    let mut baseline = Baseline::default();
+    baseline.record(&report("public", 0.001, 0.001));
+    let mut current = report("profiling", 0.002, 0.002);
+    baseline.compare(&mut current, 0.05);
+    assert!(current.groups[0]
+        .entries
+        .iter()
+        .all(|entry| entry.change.is_none()));
+}
+
+#[test]
+fn save_and_load() {
+    let path =
+        std::env::temp_dir().join(format!("arma-bench-baseline-{}.json", std::process::id()));
+    assert_eq!(
+        Baseline::load(&path).expect("Failed to load missing baseline"),
+        Baseline::default()
+    );
+    let mut baseline = Baseline::default();
+    baseline.record(&report("public", 0.001, 0.002));
+    baseline.save(&path).expect("Failed to save baseline");
+    let loaded = Baseline::load(&path).expect("Failed to load baseline");
+    std::fs::write(&path, "[]").expect("Failed to write");
+    let invalid = Baseline::load(&path);
+    std::fs::remove_file(&path).expect("Failed to remove baseline");
+    assert_eq!(loaded, baseline);
+    assert!(matches!(invalid, Err(Error::InvalidBaseline(_))));
+}
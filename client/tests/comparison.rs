@@ -0,0 +1,116 @@
+use arma_bench::{CompareResult, Comparison, Timing, Verdict};
+use arma_rs::Value;
+
+fn result(id: u16, samples: &[f64]) -> CompareResult {
+    CompareResult {
+        id,
+        time: samples[0],
+        iter: 10000,
+        ret: Value::Null,
+        logs: Vec::new(),
+        errors: Vec::new(),
+        samples: samples.to_vec(),
+        mismatches: Vec::new(),
+        timing: Timing::default(),
+    }
+}
+
+fn close(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() < tolerance
+}
+
+#[test]
+fn faster() {
+    let results = [
+        result(0, &[2.0, 2.1, 1.9, 2.0, 2.05]),
+        result(1, &[1.0, 1.1, 0.9, 1.0, 1.05]),
+    ];
+    let comparison = Comparison::new(&results, 0).expect("Missing baseline");
+    assert_eq!(comparison.ranking[0].id, 1);
+    assert_eq!(comparison.ranking[0].rank, 1);
+    let variant = comparison.get(1).expect("Missing variant");
+    assert_eq!(variant.verdict, Verdict::Faster);
+    assert!(close(variant.speedup, 2.0, 0.01));
+    assert!(variant.p_value.expect("Missing p-value") < 0.001);
+    assert!(variant.difference.high < 0.0);
+    let baseline = comparison.get(0).expect("Missing baseline");
+    assert_eq!(baseline.verdict, Verdict::Baseline);
+    assert_eq!(baseline.rank, 2);
+}
+
+#[test]
+fn slower() {
+    let results = [
+        result(3, &[1.0, 1.1, 0.9, 1.0, 1.05]),
+        result(7, &[2.0, 2.1, 1.9, 2.0, 2.05]),
+    ];
+    let comparison = Comparison::new(&results, 3).expect("Missing baseline");
+    assert_eq!(
+        comparison.get(7).expect("Missing variant").verdict,
+        Verdict::Slower
+    );
+}
+
+#[test]
+fn noise() {
+    let results = [
+        result(0, &[1.0, 1.5, 0.8, 1.2, 1.1]),
+        result(1, &[1.1, 0.9, 1.4, 1.0, 1.2]),
+    ];
+    let comparison = Comparison::new(&results, 0).expect("Missing baseline");
+    let variant = comparison.get(1).expect("Missing variant");
+    assert_eq!(variant.verdict, Verdict::Inconclusive);
+    assert!(variant.difference.low < 0.0 && variant.difference.high > 0.0);
+}
+
+#[test]
+fn welch() {
+    // The example from Wikipedia's article on Welch's t-test, t = -2.46 with p = 0.021.
+    let results = [
+        result(
+            0,
+            &[
+                27.5, 21.0, 19.0, 23.6, 17.0, 17.9, 16.9, 20.1, 21.9, 22.6, 23.1, 19.6, 19.0, 21.7,
+                21.4,
+            ],
+        ),
+        result(
+            1,
+            &[
+                27.1, 22.0, 20.8, 23.4, 23.4, 23.5, 25.8, 22.0, 24.8, 20.2, 21.9, 22.1, 22.9, 20.5,
+                24.4,
+            ],
+        ),
+    ];
+    let comparison = Comparison::new(&results, 0).expect("Missing baseline");
+    let p = comparison
+        .get(1)
+        .and_then(|variant| variant.p_value)
+        .expect("Missing p-value");
+    assert!(close(p, 0.021, 0.001), "p = {p}");
+}
+
+#[test]
+fn interval() {
+    let results = [result(0, &[1.0, 2.0, 3.0, 4.0, 5.0])];
+    let comparison = Comparison::new(&results, 0).expect("Missing baseline");
+    let interval = comparison.ranking[0].interval;
+    // The mean plus or minus t(0.975, 4) * s / sqrt(n).
+    assert!(close(interval.low, 1.0368, 0.0005), "{interval:?}");
+    assert!(close(interval.high, 4.9632, 0.0005), "{interval:?}");
+}
+
+#[test]
+fn single_samples() {
+    let results = [result(0, &[2.0]), result(1, &[1.0])];
+    let comparison = Comparison::new(&results, 0).expect("Missing baseline");
+    let variant = comparison.get(1).expect("Missing variant");
+    assert!(variant.p_value.is_none());
+    assert_eq!(variant.verdict, Verdict::Inconclusive);
+    assert!(close(variant.speedup, 2.0, f64::EPSILON));
+}
+
+#[test]
+fn missing_baseline() {
+    assert!(Comparison::new(&[result(0, &[1.0])], 1).is_none());
+}
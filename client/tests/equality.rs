@@ -0,0 +1,58 @@
+use arma_bench::{mismatches, Mismatch};
+use arma_rs::Value;
+
+#[test]
+fn equal() {
+    let value = Value::Array(vec![
+        Value::Number(1.0),
+        Value::String("a".to_string()),
+        Value::Array(vec![Value::Boolean(true), Value::Null]),
+    ]);
+    assert!(mismatches(&value, &value.clone(), 0.0).is_empty());
+}
+
+#[test]
+fn tolerance() {
+    let expected = Value::Number(0.1 + 0.2);
+    assert!(mismatches(&expected, &Value::Number(0.3), 1e-9).is_empty());
+    assert_eq!(mismatches(&expected, &Value::Number(0.31), 1e-9).len(), 1);
+    assert!(mismatches(&Value::Number(f64::NAN), &Value::Number(f64::NAN), 0.0).is_empty());
+    assert!(mismatches(
+        &Value::Number(f64::INFINITY),
+        &Value::Number(f64::INFINITY),
+        0.0
+    )
+    .is_empty());
+}
+
+#[test]
+fn nested() {
+    let expected = Value::Array(vec![
+        Value::Number(1.0),
+        Value::Array(vec![Value::Number(2.0), Value::Number(3.0)]),
+    ]);
+    let actual = Value::Array(vec![
+        Value::Number(1.0),
+        Value::Array(vec![Value::Number(2.0), Value::String("3".to_string())]),
+    ]);
+    let found = mismatches(&expected, &actual, 0.0);
+    assert_eq!(
+        found,
+        [Mismatch {
+            path: vec![1, 1],
+            expected: Value::Number(3.0),
+            actual: Value::String("3".to_string()),
+        }]
+    );
+    assert!(found[0].to_string().starts_with("[1][1]: expected "));
+}
+
+#[test]
+fn lengths() {
+    let expected = Value::Array(vec![Value::Number(1.0)]);
+    let actual = Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]);
+    let found = mismatches(&expected, &actual, 0.0);
+    // The whole array is reported, not each element.
+    assert_eq!(found.len(), 1);
+    assert!(found[0].path.is_empty());
+}
@@ -0,0 +1,123 @@
+use std::io::{Cursor, Read};
+
+use arma_bench::{
+    Error, ExecuteResult, Message, ScriptError, ServerConfig, Timing, DEFAULT_MAX_MESSAGE_SIZE,
+};
+use arma_rs::Value;
+
+/// Counts how many bytes were read from the inner reader.
+struct Counting<R> {
+    inner: R,
+    read: usize,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n;
+        Ok(n)
+    }
+}
+
+fn framed(config: &ServerConfig) -> Vec<u8> {
+    let mut buf = Vec::new();
+    config.write(&mut buf).expect("Failed to write");
+    buf
+}
+
+#[test]
+fn roundtrip() {
+    let buf = framed(&ServerConfig::default());
+    let config = ServerConfig::from_reader(&mut Cursor::new(buf)).expect("Failed to read");
+    assert_eq!(config.binary, ServerConfig::default().binary);
+}
+
+#[test]
+fn oversized_prefix() {
+    // The payload would be far too large to allocate, only the prefix is present.
+    let mut reader = Counting {
+        inner: Cursor::new(u64::MAX.to_le_bytes()),
+        read: 0,
+    };
+    let res = ServerConfig::from_reader(&mut reader);
+    assert!(matches!(
+        res,
+        Err(Error::MessageTooLarge { size: u64::MAX, max }) if max == DEFAULT_MAX_MESSAGE_SIZE
+    ));
+    assert_eq!(reader.read, 8);
+}
+
+#[test]
+fn custom_limit() {
+    let buf = framed(&ServerConfig::default());
+    let len = buf.len() as u64 - 8;
+    let res = ServerConfig::from_reader_with_limit(&mut Cursor::new(&buf), len - 1);
+    assert!(matches!(res, Err(Error::MessageTooLarge { size, .. }) if size == len));
+    ServerConfig::from_reader_with_limit(&mut Cursor::new(&buf), len).expect("Failed to read");
+}
+
+#[test]
+fn truncated_payload() {
+    let mut buf = framed(&ServerConfig::default());
+    buf.truncate(buf.len() - 1);
+    let res = ServerConfig::from_reader(&mut Cursor::new(buf));
+    assert!(matches!(res, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof));
+}
+
+fn result() -> ExecuteResult {
+    ExecuteResult {
+        time: 0.5,
+        iter: 10,
+        ret: Value::Number(1.0),
+        logs: Vec::new(),
+        errors: vec![ScriptError {
+            file: "bench.sqf".to_string(),
+            line: Some(1),
+            message: "Missing ;".to_string(),
+        }],
+        samples: Vec::new(),
+        timing: Timing::default(),
+    }
+}
+
+#[test]
+fn skipped_fields() {
+    // The empty logs are left out, the errors after them must still line up.
+    let mut buf = Vec::new();
+    result().write(&mut buf).expect("Failed to write");
+    let read = ExecuteResult::from_reader(&mut Cursor::new(buf)).expect("Failed to read");
+    assert_eq!(read.errors, result().errors);
+    assert!(read.logs.is_empty());
+}
+
+#[test]
+fn unknown_fields() {
+    // How an older peer sees a result, before logs and errors were added.
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Older {
+        time: f64,
+        iter: u32,
+    }
+    impl Message for Older {}
+
+    let mut buf = Vec::new();
+    result().write(&mut buf).expect("Failed to write");
+    let older = Older::from_reader(&mut Cursor::new(buf)).expect("Failed to read");
+    assert!((older.time - 0.5).abs() < f64::EPSILON);
+    assert_eq!(older.iter, 10);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_oversized_prefix() {
+    let mut reader = Cursor::new(u64::MAX.to_le_bytes());
+    let res = ServerConfig::from_async_reader_with_limit(&mut reader, 1024).await;
+    assert!(matches!(
+        res,
+        Err(Error::MessageTooLarge {
+            size: u64::MAX,
+            max: 1024
+        })
+    ));
+    assert_eq!(reader.position(), 8);
+}
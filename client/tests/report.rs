@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+
+use arma_bench::{
+    Baseline, CompareResult, ExecuteResult, Group, Mismatch, Report, ReportFormat, ScriptError,
+    Statistics, Thresholds, Timing, DEFAULT_TOLERANCE,
+};
+use arma_rs::Value;
+
+fn compare_result(id: u16, samples: &[f64], ret: Value) -> CompareResult {
+    CompareResult {
+        id,
+        time: samples[0],
+        iter: 10000,
+        ret,
+        logs: Vec::new(),
+        errors: Vec::new(),
+        samples: samples.to_vec(),
+        mismatches: Vec::new(),
+        timing: Timing::default(),
+    }
+}
+
+fn report() -> Report {
+    let mut slow = compare_result(
+        2,
+        &[0.004, 0.004, 0.004],
+        Value::Array(vec![Value::Number(1.0), Value::String("a|b".to_string())]),
+    );
+    slow.errors.push(ScriptError {
+        file: "2.sqf".to_string(),
+        line: Some(3),
+        message: "Undefined variable in expression: _b".to_string(),
+    });
+    slow.mismatches.push(Mismatch {
+        path: vec![1],
+        expected: Value::String("ab".to_string()),
+        actual: Value::String("a|b".to_string()),
+    });
+    let results = [
+        compare_result(0, &[0.002, 0.002, 0.002], Value::Number(3.0)),
+        compare_result(1, &[0.001, 0.001, 0.001], Value::Number(3.0)),
+        slow,
+    ];
+    let scripts = ["base.sqf", "fast.sqfc", "slow <old>.sqf"].map(ToString::to_string);
+    let execute = ExecuteResult {
+        time: 0.5,
+        iter: 200,
+        ret: Value::Boolean(true),
+        logs: Vec::new(),
+        errors: Vec::new(),
+        samples: vec![0.5],
+        timing: Timing::default(),
+    };
+    let mut report = Report {
+        groups: vec![
+            Group {
+                branch: Some("profiling".to_string()),
+                ..Group::compare("count & select", &scripts, &results, 0).expect("Missing baseline")
+            },
+            Group::execute("init.sqf", &execute),
+        ],
+    };
+    report.check(&Thresholds {
+        max_mean: Some(0.25),
+        min_speedup: None,
+    });
+    // fast.sqfc was twice as slow before, init.sqf has no baseline.
+    let mut before = report.groups[0].clone();
+    before.entries[0].statistics = Statistics::new(vec![0.002; 3]).expect("No samples");
+    let mut baseline = Baseline::default();
+    baseline.record(&Report {
+        groups: vec![before],
+    });
+    baseline.compare(&mut report, DEFAULT_TOLERANCE);
+    report
+}
+
+/// Compare with the golden file, or write it when `UPDATE_GOLDEN` is set.
+fn golden(format: ReportFormat, file: &str) {
+    let mut out = Vec::new();
+    report().write(format, &mut out).expect("Failed to write");
+    let out = String::from_utf8(out).expect("Report is not UTF-8");
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(file);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, out).expect("Failed to write golden file");
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).expect("Failed to read golden file");
+    assert_eq!(
+        out, expected,
+        "{file} differs, run with UPDATE_GOLDEN=1 to accept"
+    );
+}
+
+#[test]
+fn json() {
+    golden(ReportFormat::Json, "report.json");
+}
+
+#[test]
+fn csv() {
+    golden(ReportFormat::Csv, "report.csv");
+}
+
+#[test]
+fn markdown() {
+    golden(ReportFormat::Markdown, "report.md");
+}
+
+#[test]
+fn markdown_multiline() {
+    let mut result = ExecuteResult {
+        time: 0.5,
+        iter: 200,
+        ret: Value::String("first\nsecond".to_string()),
+        logs: Vec::new(),
+        errors: Vec::new(),
+        samples: vec![0.5],
+        timing: Timing::default(),
+    };
+    result.errors.push(ScriptError {
+        file: "bench.sqf".to_string(),
+        line: None,
+        message: "Error in expression\r\n|#|_x".to_string(),
+    });
+    let mut report = Report {
+        groups: vec![Group::execute("multi\nline.sqf", &result)],
+    };
+    report.check(&Thresholds::default());
+    let mut out = Vec::new();
+    report.write_markdown(&mut out).expect("Failed to write");
+    let out = String::from_utf8(out).expect("Report is not UTF-8");
+    // A heading, a blank line, and a table of one script.
+    assert_eq!(out.lines().count(), 5);
+    assert!(out.lines().skip(2).all(|line| line.starts_with('|')));
+}
+
+#[test]
+fn failed_group() {
+    let mut report = report();
+    report.groups.push(Group::failed(
+        "broken",
+        vec!["bench.sqf failed to compile".to_string()],
+    ));
+    assert_eq!(report.failures(), 3);
+    let written = |format| {
+        let mut out = Vec::new();
+        report.write(format, &mut out).expect("Failed to write");
+        String::from_utf8(out).expect("Report is not UTF-8")
+    };
+    assert!(
+        written(ReportFormat::Markdown).ends_with("### broken\n\n❌ bench.sqf failed to compile\n")
+    );
+    assert!(
+        written(ReportFormat::Csv).ends_with("broken,,,,,,,,,,,,,bench.sqf failed to compile,,,\n")
+    );
+    let junit = written(ReportFormat::Junit);
+    assert!(
+        junit.contains(r#"<testsuites name="arma-bench" tests="5" failures="3">"#),
+        "{junit}"
+    );
+    assert!(
+        junit.contains(r#"<failure message="bench.sqf failed to compile"/>"#),
+        "{junit}"
+    );
+}
+
+#[test]
+fn junit() {
+    golden(ReportFormat::Junit, "report.xml");
+}
+
+#[test]
+fn json_round_trip() {
+    let report = report();
+    let mut out = Vec::new();
+    report.write_json(&mut out).expect("Failed to write");
+    let read: Report = serde_json::from_slice(&out).expect("Failed to read");
+    assert_eq!(read, report);
+}
+
+#[test]
+fn failures() {
+    let report = report();
+    assert!(report.failed());
+    let failures = report
+        .groups
+        .iter()
+        .flat_map(|group| &group.entries)
+        .map(|entry| (entry.name.as_str(), entry.failures.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        [
+            ("fast.sqfc", 0),
+            ("base.sqf", 0),
+            ("slow <old>.sqf", 2),
+            ("init.sqf", 1)
+        ]
+    );
+    let mut passing = Report {
+        groups: vec![report.groups[0].clone()],
+    };
+    passing.groups[0].entries.truncate(2);
+    assert!(!passing.failed());
+}
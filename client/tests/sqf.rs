@@ -0,0 +1,26 @@
+use arma_bench::to_sqf;
+use arma_rs::Value;
+
+#[test]
+fn literals() {
+    let value = Value::Array(vec![
+        Value::Number(1.5),
+        Value::String("say \"hi\"".to_string()),
+        Value::Boolean(true),
+        Value::Null,
+        Value::Array(Vec::new()),
+    ]);
+    assert_eq!(
+        to_sqf(&value).as_deref(),
+        Some(r#"[1.5, "say ""hi""", true, nil, []]"#)
+    );
+}
+
+#[test]
+fn non_finite() {
+    assert_eq!(to_sqf(&Value::Number(f64::NAN)), None);
+    assert_eq!(
+        to_sqf(&Value::Array(vec![Value::Number(f64::INFINITY)])),
+        None
+    );
+}
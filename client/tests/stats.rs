@@ -0,0 +1,46 @@
+use arma_bench::Statistics;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn summary() {
+    let stats = Statistics::new(vec![4.0, 1.0, 3.0, 2.0, 5.0]).expect("No samples");
+    assert!(close(stats.min, 1.0));
+    assert!(close(stats.max, 5.0));
+    assert!(close(stats.mean, 3.0));
+    assert!(close(stats.median, 3.0));
+    assert!(close(stats.stddev, 2.5_f64.sqrt()));
+    // Kept in the order they were taken.
+    assert_eq!(stats.samples, [4.0, 1.0, 3.0, 2.0, 5.0]);
+}
+
+#[test]
+fn even_median() {
+    let stats = Statistics::new(vec![1.0, 2.0, 3.0, 10.0]).expect("No samples");
+    assert!(close(stats.median, 2.5));
+}
+
+#[test]
+fn percentiles() {
+    let stats = Statistics::new((1..=11).map(f64::from).collect()).expect("No samples");
+    assert!(close(stats.percentile(0.0), 1.0));
+    assert!(close(stats.percentile(50.0), 6.0));
+    assert!(close(stats.percentile(90.0), 10.0));
+    assert!(close(stats.percentile(95.0), 10.5));
+    assert!(close(stats.percentile(100.0), 11.0));
+}
+
+#[test]
+fn single() {
+    let stats = Statistics::new(vec![0.5]).expect("No samples");
+    assert!(close(stats.median, 0.5));
+    assert!(close(stats.stddev, 0.0));
+    assert!(close(stats.percentile(99.0), 0.5));
+}
+
+#[test]
+fn empty() {
+    assert!(Statistics::new(Vec::new()).is_none());
+}
@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use arma_bench::{Error, Parameters, Suite, Thresholds};
+use arma_rs::Value;
+
+fn dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/suite"))
+}
+
+fn invalid(manifest: &str) -> String {
+    match Suite::parse(manifest, dir()) {
+        Err(Error::InvalidSuite(message)) => message,
+        res => panic!("Manifest was accepted: {res:?}"),
+    }
+}
+
+#[test]
+fn load() {
+    let suite = Suite::load(&dir().join("suite.toml")).expect("Failed to load suite");
+    assert_eq!(suite.name.as_deref(), Some("arrays"));
+    assert_eq!(suite.branch.as_deref(), Some("profiling"));
+    assert_eq!(suite.repetitions, Some(10));
+    assert_eq!(
+        suite.thresholds,
+        Thresholds {
+            max_mean: None,
+            min_speedup: Some(0.5),
+        }
+    );
+    assert_eq!(suite.benchmarks.len(), 2);
+
+    let count = &suite.benchmarks[0];
+    assert_eq!(
+        count.parameters,
+        [10.0, 1000.0].map(|size| Parameters::from([("_size".to_string(), Value::Number(size))]))
+    );
+    assert_eq!(count.baseline, 1);
+    assert_eq!(count.expect, Some(Value::Number(1000.0)));
+    assert!(!count.assert_equal);
+    assert_eq!(count.variants[0].content, b"count _array");
+    assert_eq!(count.variants[1].name, "forEach");
+    assert!(!count.variants[1].sqfc);
+    assert!(String::from_utf8_lossy(&count.variants[1].content).contains("forEach _array"));
+
+    let sum = &suite.benchmarks[1];
+    assert_eq!(sum.branch.as_deref(), Some("public"));
+    assert_eq!(
+        sum.setup.as_deref(),
+        Some("private _numbers = [1, 2, 3];\n")
+    );
+    assert!(sum.assert_equal);
+    assert!((sum.tolerance - 0.001).abs() < f64::EPSILON);
+}
+
+#[test]
+fn unknown_fields() {
+    let message = invalid(
+        r#"
+[[benchmark]]
+name = "a"
+repetition = 2
+
+[[benchmark.variant]]
+name = "a"
+code = "1"
+"#,
+    );
+    assert!(message.contains("repetition"), "{message}");
+}
+
+#[test]
+fn no_benchmarks() {
+    assert_eq!(invalid(""), "there are no benchmarks");
+}
+
+#[test]
+fn no_variants() {
+    assert_eq!(
+        invalid("[[benchmark]]\nname = \"a\"\n"),
+        "benchmark a: there are no variants"
+    );
+}
+
+#[test]
+fn variant_code_and_file() {
+    let message = invalid(
+        r#"
+[[benchmark]]
+name = "a"
+
+[[benchmark.variant]]
+code = "1"
+file = "foreach.sqf"
+"#,
+    );
+    assert!(message.contains("either code or a file"), "{message}");
+}
+
+#[test]
+fn missing_file() {
+    let message = invalid(
+        r#"
+[[benchmark]]
+name = "a"
+
+[[benchmark.variant]]
+file = "missing.sqf"
+"#,
+    );
+    assert!(message.contains("missing.sqf"), "{message}");
+}
+
+#[test]
+fn unknown_baseline() {
+    let message = invalid(
+        r#"
+[[benchmark]]
+name = "a"
+baseline = "c"
+
+[[benchmark.variant]]
+name = "b"
+code = "1"
+"#,
+    );
+    assert!(message.contains("baseline c"), "{message}");
+}
+
+#[test]
+fn invalid_parameters() {
+    for parameters in [
+        "{ size = 1 }",
+        "{ _a-b = 1 }",
+        "{ _size = nan }",
+        "{ _size = { a = 1 } }",
+        "{ _date = 1979-05-27 }",
+        "{ _samples = 1 }",
+    ] {
+        let message = invalid(&format!(
+            r#"
+[[benchmark]]
+name = "a"
+parameters = [{parameters}]
+
+[[benchmark.variant]]
+name = "b"
+code = "1"
+"#
+        ));
+        assert!(message.starts_with("benchmark a: "), "{message}");
+    }
+}
@@ -1,18 +1,48 @@
 use std::{io::Write, path::PathBuf};
 
-use arma_bench::{CompareResult, ExecuteResult};
+use arma_bench::{CompareResult, ExecuteResult, Timing};
 use arma_rs::{arma, Extension, Value};
 
+/// The time of one iteration and how many ran, as `diag_codePerformance` returns them.
+type Sample = (f64, u32);
+
 #[arma]
 fn init() -> Extension {
     Extension::build()
+        .command("started", started)
         .command("timeout", timeout)
+        .command("compile_error", compile_error)
         .command("execute", execute)
         .command("compare", compare)
         .command("die", die)
         .finish()
 }
 
+#[allow(clippy::needless_pass_by_value)]
+fn started(id: String) {
+    // lets the server tell a crash during startup from one during the benchmark
+    std::fs::File::create(
+        PathBuf::from("/tmp/arma_bench")
+            .join(&id)
+            .join("started.txt"),
+    )
+    .expect("Failed to create started.txt");
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn compile_error(id: String, file: String) {
+    // the errors themselves are in the RPT, the server only needs to know which file
+    std::fs::File::create(
+        PathBuf::from("/tmp/arma_bench")
+            .join(&id)
+            .join("compile_error.txt"),
+    )
+    .expect("Failed to create compile_error.txt")
+    .write_all(file.as_bytes())
+    .expect("Failed to write compile_error.txt");
+    die();
+}
+
 #[allow(clippy::needless_pass_by_value)]
 fn timeout(id: String, time: u64) {
     std::thread::spawn(move || {
@@ -31,7 +61,7 @@ fn timeout(id: String, time: u64) {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn execute(id: String, data: (f64, u32), value: Value) {
+fn execute(id: String, samples: Vec<Sample>, value: Value) {
     {
         let mut out = std::fs::File::create(
             PathBuf::from("/tmp/arma_bench")
@@ -39,17 +69,23 @@ fn execute(id: String, data: (f64, u32), value: Value) {
                 .join("execute.txt"),
         )
         .expect("Failed to create execute.txt");
+        let (time, iter) = samples.first().copied().unwrap_or_default();
         let data = ExecuteResult {
-            time: data.0,
-            iter: data.1,
+            time,
+            iter,
             ret: value,
+            logs: Vec::new(),
+            errors: Vec::new(),
+            samples: samples.into_iter().map(|(time, _)| time).collect(),
+            // The server knows which it asked for.
+            timing: Timing::default(),
         };
         serde_json::to_writer(&mut out, &data).expect("Failed to write execute.txt");
     }
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn compare(id: String, data: Vec<(String, (f64, u32), Value)>) {
+fn compare(id: String, data: Vec<(String, Vec<Sample>, Value)>) {
     {
         let mut out = std::fs::File::create(
             PathBuf::from("/tmp/arma_bench")
@@ -59,11 +95,19 @@ fn compare(id: String, data: Vec<(String, (f64, u32), Value)>) {
         .expect("Failed to create compare.txt");
         let data = data
             .into_iter()
-            .map(|(id, (time, iter), ret)| CompareResult {
-                id: id.parse().expect("Failed to parse ID"),
-                time,
-                iter,
-                ret,
+            .map(|(id, samples, ret)| {
+                let (time, iter) = samples.first().copied().unwrap_or_default();
+                CompareResult {
+                    id: id.parse().expect("Failed to parse ID"),
+                    time,
+                    iter,
+                    ret,
+                    logs: Vec::new(),
+                    errors: Vec::new(),
+                    samples: samples.into_iter().map(|(time, _)| time).collect(),
+                    mismatches: Vec::new(),
+                    timing: Timing::default(),
+                }
             })
             .collect::<Vec<_>>();
         serde_json::to_writer(&mut out, &data).expect("Failed to write compare.txt");
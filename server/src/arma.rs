@@ -1,71 +1,287 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
 
-use arma_bench::ServerConfig;
-use tokio::process::{Child, Command};
-use tracing::debug;
+use arma_bench::{Progress, ServerConfig};
+use tokio::{
+    process::{Child, Command},
+    sync::{OwnedRwLockReadGuard, RwLock},
+};
+use tracing::{debug, error};
 use uuid::Uuid;
 
-use crate::build::BuiltRequest;
-
-pub async fn install(config: &ServerConfig) -> Result<PathBuf, String> {
-    // check if there is a server at the path defined
-    let fs_branch = config.branch.to_lowercase();
-    let path = PathBuf::from("/opt/servers").join(&fs_branch);
-    // if the path exists and is less than 12 hours old, return it
-    if path.exists() {
-        if let Ok(metadata) = path.metadata() {
-            if let Ok(modified) = metadata.modified() {
-                if let Ok(elapsed) = modified.elapsed() {
-                    if elapsed.as_secs() < 43200 {
-                        debug!("Using existing server {} at {:?}", fs_branch, path);
-                        return Ok(path);
-                    }
-                }
-            }
+use crate::{build::BuiltRequest, worker::Worker, ProgressSender};
+
+/// Provides an Arma server install for a [`ServerConfig`].
+pub trait Installer: Send + Sync + 'static {
+    /// Install or update the server, returning the directory it is installed in.
+    fn install(
+        &self,
+        config: &ServerConfig,
+    ) -> impl Future<Output = Result<Install, String>> + Send;
+}
+
+/// A server install, which is not updated while this is kept.
+pub struct Install {
+    pub path: PathBuf,
+    _in_use: Option<OwnedRwLockReadGuard<()>>,
+}
+
+impl Install {
+    /// An install that nothing else updates.
+    #[must_use]
+    pub const fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            _in_use: None,
+        }
+    }
+}
+
+/// A process started by a [`Launcher`].
+pub struct Launched {
+    pub child: Child,
+    /// Kept until the process exits, so its install is not updated under it.
+    pub install: Option<Install>,
+}
+
+impl From<Child> for Launched {
+    fn from(child: Child) -> Self {
+        Self {
+            child,
+            install: None,
+        }
+    }
+}
+
+/// Starts a process that runs a built request and writes its results next to it.
+///
+/// Launchers should report [`Progress::Installing`] and [`Progress::Starting`] where they apply,
+/// start the process in its own process group so it can be killed with anything it starts,
+/// and write its output to [`BuiltRequest::output`].
+pub trait Launcher: Send + Sync + 'static {
+    fn launch(
+        &self,
+        worker: &Worker,
+        config: &ServerConfig,
+        built: &BuiltRequest,
+        progress: &ProgressSender,
+    ) -> impl Future<Output = Result<Launched, String>> + Send;
+}
+
+/// Installs servers with steamcmd, reusing installs that are less than 12 hours old.
+pub struct SteamCmd {
+    /// Path to `steamcmd.sh`.
+    pub steamcmd: PathBuf,
+    /// Directory containing a server install per branch.
+    pub servers: PathBuf,
+    /// Read while a branch is used and written while it is installed,
+    /// so it is never updated under a running server or by two workers at once.
+    installs: Mutex<HashMap<String, Arc<RwLock<()>>>>,
+}
+
+impl SteamCmd {
+    #[must_use]
+    pub fn new(steamcmd: PathBuf, servers: PathBuf) -> Self {
+        Self {
+            steamcmd,
+            servers,
+            installs: Mutex::default(),
+        }
+    }
+}
+
+impl Default for SteamCmd {
+    fn default() -> Self {
+        Self::new(
+            PathBuf::from("/steamcmd/steamcmd.sh"),
+            PathBuf::from("/opt/servers"),
+        )
+    }
+}
+
+/// Written once an install finishes, dating it.
+const INSTALLED: &str = ".tab-installed";
+/// Kept while an install runs, so one that was interrupted is never used.
+const INSTALLING: &str = ".tab-installing";
+
+/// Whether the install at `path` finished less than 12 hours ago.
+/// Installs from before [`INSTALLED`] was written are dated by their directory.
+fn fresh(path: &Path) -> bool {
+    if path.join(INSTALLING).exists() {
+        return false;
+    }
+    let installed = path.join(INSTALLED);
+    if installed.exists() {
+        installed
+    } else {
+        path.to_path_buf()
+    }
+    .metadata()
+    .and_then(|metadata| metadata.modified())
+    .ok()
+    .and_then(|modified| modified.elapsed().ok())
+    .is_some_and(|elapsed| elapsed.as_secs() < 43200)
+}
+
+impl Installer for SteamCmd {
+    async fn install(&self, config: &ServerConfig) -> Result<Install, String> {
+        // check if there is a server at the path defined
+        let fs_branch = config.branch.to_lowercase();
+        let path = self.servers.join(&fs_branch);
+        let lock = self
+            .installs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(fs_branch.clone())
+            .or_default()
+            .clone();
+        let in_use = lock.clone().read_owned().await;
+        if fresh(&path) {
+            debug!("Using existing server {} at {:?}", fs_branch, path);
+            return Ok(Install {
+                path,
+                _in_use: Some(in_use),
+            });
+        }
+        drop(in_use);
+        // Waits for the servers running on the branch to exit.
+        let installing = lock.write_owned().await;
+        // Checked again, another worker may have just installed it.
+        if !fresh(&path) {
+            self.download(config, &fs_branch, &path).await?;
+        }
+        Ok(Install {
+            path,
+            _in_use: Some(installing.downgrade()),
+        })
+    }
+}
+
+impl SteamCmd {
+    /// Run steamcmd to install or update `config`'s branch into `path`.
+    async fn download(
+        &self,
+        config: &ServerConfig,
+        fs_branch: &str,
+        path: &Path,
+    ) -> Result<(), String> {
+        let steam_user = std::env::var("STEAM_USER").map_err(|_| "STEAM_USER not set")?;
+        let steam_pass = std::env::var("STEAM_PASS").map_err(|_| "STEAM_PASS not set")?;
+        // otherwise, download the server and return the path
+        debug!("Downloading {} server to {:?}", fs_branch, path);
+        tokio::fs::create_dir_all(path)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::write(path.join(INSTALLING), "")
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut command = Command::new(&self.steamcmd);
+        // A cancelled request stops the install with it.
+        command
+            .kill_on_drop(true)
+            .arg("+force_install_dir")
+            .arg(path)
+            .arg(format!("+login {steam_user} {steam_pass}"))
+            .arg("+app_update 233780");
+        if config.branch != "public" {
+            debug!("Using branch {}", config.branch);
+            command.arg("-beta").arg(&config.branch);
         }
+        if !config.branch_password.is_empty() {
+            debug!("Using branch password {}", config.branch_password);
+            command.arg("-betapassword").arg(&config.branch_password);
+        }
+        let command = command
+            .arg("validate")
+            .arg("+quit")
+            .output()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !command.status.success() {
+            return Err(format!("Failed to install server: {command:?}"));
+        }
+        tokio::fs::write(path.join(INSTALLED), "")
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::remove_file(path.join(INSTALLING))
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Launches the real Arma server binary with the `tab` extension loaded.
+pub struct Arma<I> {
+    installer: I,
+}
+
+impl<I: Installer> Arma<I> {
+    pub const fn new(installer: I) -> Self {
+        Self { installer }
     }
-    let steam_user = std::env::var("STEAM_USER").map_err(|_| "STEAM_USER not set")?;
-    let steam_pass = std::env::var("STEAM_PASS").map_err(|_| "STEAM_PASS not set")?;
-    // otherwise, download the server and return the path
-    debug!("Downloading {} server to {:?}", fs_branch, path);
-    let mut command = Command::new("/steamcmd/steamcmd.sh");
-    command
-        .arg("+force_install_dir")
-        .arg(&path)
-        .arg(format!("+login {steam_user} {steam_pass}"))
-        .arg("+app_update 233780");
-    if config.branch != "public" {
-        debug!("Using branch {}", config.branch);
-        command.arg("-beta").arg(&config.branch);
+}
+
+impl<I: Installer> Launcher for Arma<I> {
+    async fn launch(
+        &self,
+        worker: &Worker,
+        config: &ServerConfig,
+        built: &BuiltRequest,
+        progress: &ProgressSender,
+    ) -> Result<Launched, String> {
+        let _ = progress.send(Progress::Installing);
+        let install = self.installer.install(config).await?;
+        let path = &install.path;
+        let profiles = worker.profiles();
+        tokio::fs::create_dir_all(&profiles)
+            .await
+            .map_err(|e| e.to_string())?;
+        let _ = progress.send(Progress::Starting);
+        let name = Uuid::new_v4();
+        let binary = path.join(&config.binary);
+        let output = std::fs::File::create(built.output()).map_err(|e| e.to_string())?;
+        let mut command = if let Some(cpus) = &worker.cpus {
+            let cpus = cpus.iter().map(ToString::to_string).collect::<Vec<_>>();
+            debug!("Pinning to CPUs {}", cpus.join(","));
+            let mut command = Command::new("taskset");
+            command.arg("-c").arg(cpus.join(",")).arg(binary);
+            command
+        } else {
+            Command::new(binary)
+        };
+        let child = command
+            .process_group(0)
+            .stdout(output.try_clone().map_err(|e| e.to_string())?)
+            .stderr(output)
+            .current_dir(path)
+            .arg(format!("-name={name}"))
+            .arg("-world=empty")
+            .arg("-limitFPS=1000")
+            .arg(format!("-profiles=\"{}\"", profiles.to_string_lossy()))
+            .arg("-mod=\"../../@tab\"")
+            .arg(format!("\"-mod=../../..{}\"", built.path.to_string_lossy()))
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        Ok(Launched {
+            child,
+            install: Some(install),
+        })
     }
-    if !config.branch_password.is_empty() {
-        debug!("Using branch password {}", config.branch_password);
-        command.arg("-betapassword").arg(&config.branch_password);
+}
+
+/// Kill a launched process along with the rest of its process group.
+pub async fn kill(child: &mut Child) {
+    if let Some(pid) = child.id().and_then(|pid| i32::try_from(pid).ok()) {
+        // SAFETY: `kill` has no memory safety requirements, a negative PID targets the group.
+        // If the launcher did not start a new group this fails with ESRCH, and only the child is killed below.
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
     }
-    let command = command
-        .arg("validate")
-        .arg("+quit")
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
-    if !command.status.success() {
-        return Err(format!("Failed to install server: {command:?}"));
+    if let Err(e) = child.kill().await {
+        error!(error = %e, "Failed to kill server");
     }
-    Ok(path)
-}
-
-pub async fn start(config: &ServerConfig, built: &BuiltRequest) -> Result<(Uuid, Child), String> {
-    let path = install(config).await?;
-    let name = Uuid::new_v4();
-    let command = Command::new(path.join(&config.binary))
-        .current_dir(&path)
-        .arg(format!("-name={name}"))
-        .arg("-world=empty")
-        .arg("-limitFPS=1000")
-        .arg("-profiles=\"/tmp/arma_profiles\"")
-        .arg("-mod=\"../../@tab\"")
-        .arg(format!("\"-mod=../../..{}\"", built.path.to_string_lossy()))
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    Ok((name, command))
 }
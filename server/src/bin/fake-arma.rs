@@ -0,0 +1,294 @@
+//! Stands in for the Arma server binary in tests.
+//!
+//! Takes the directory of a built request, reads the scripts out of its PBO and
+//! writes results the same way the `tab` extension does, without evaluating any SQF.
+//! The result of a script is its content, as a number if it parses as one.
+//! Each repetition takes a little longer than the last, so the samples differ.
+//!
+//! Output is written like Arma's log, with the version it reports on startup
+//! and the markers the bootstrap logs around the benchmark.
+//! Setup and teardown scripts are read for directives before and after each script.
+//! A matrix substitutes the parameters the bootstrap declares into the script before running it,
+//! and `_this` is replaced by the arguments run.sqf passes.
+//! Each iteration count is the cycles run.sqf asks for.
+//!
+//! Scripts can contain directives to simulate failures:
+//! - `fake:timeout` behaves as if the in-game timeout fired
+//! - `fake:sleep=<ms>` takes that long to run the script
+//! - `fake:hang` never finishes, as if Arma hung before the extension loaded
+//! - `fake:never-start` exits before the benchmark starts, or hangs with `fake:hang`
+//! - `fake:crash` aborts, as if Arma crashed
+//! - `fake:exit=<code>` exits with that code without a result
+//! - `fake:no-result` exits cleanly without a result
+//! - `fake:log=<text>` logs the rest of the line
+//! - `fake:error=<message>` logs a script error on that line
+//! - `fake:compile-error` fails to compile, as a syntax error would
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use arma_bench::{CompareResult, ExecuteResult, Timing};
+use arma_rs::Value;
+use hemtt_pbo::ReadablePbo;
+
+fn main() {
+    let path = PathBuf::from(std::env::args().nth(1).expect("Missing request path"));
+    let mut pbo = ReadablePbo::from(
+        File::open(path.join("addons").join("execute.pbo")).expect("Failed to open PBO"),
+    )
+    .expect("Failed to read PBO");
+    let bootstrap = read(&mut pbo, "bootstrap.sqf").expect("Missing bootstrap.sqf");
+    println!("fake-arma starting");
+    if let Some(content) = never_starts(&mut pbo) {
+        if content.contains("fake:hang") {
+            hang();
+        }
+        return;
+    }
+    let repetitions = argument(&bootstrap, "_repetitions = ").unwrap_or(1);
+    std::fs::write(path.join("started.txt"), "").expect("Failed to write started.txt");
+    println!("Version: 2.18.152405");
+    println!("\"tab: begin\"");
+
+    let setup = read(&mut pbo, "setup.sqf").unwrap_or_default();
+    let teardown = read(&mut pbo, "teardown.sqf").unwrap_or_default();
+    let run = read(&mut pbo, "run.sqf").expect("Missing run.sqf");
+
+    if let Some(content) = read(&mut pbo, "bench.sqf") {
+        if bootstrap.contains(MATRIX_MARKER) {
+            matrix(
+                &path,
+                &bootstrap,
+                &run,
+                &content,
+                &setup,
+                &teardown,
+                repetitions,
+            );
+            return;
+        }
+        directives(&path, &bootstrap, "setup.sqf", &setup);
+        directives(&path, &bootstrap, "bench.sqf", &content);
+        directives(&path, &bootstrap, "teardown.sqf", &teardown);
+        let samples = samples(0.001, repetitions);
+        let result = ExecuteResult {
+            time: samples[0],
+            iter: cycles(&run),
+            ret: value(&with_arguments(&content, &run)),
+            logs: Vec::new(),
+            errors: Vec::new(),
+            samples,
+            timing: Timing::default(),
+        };
+        println!("\"tab: end\"");
+        let out = File::create(path.join("execute.txt")).expect("Failed to create execute.txt");
+        serde_json::to_writer(out, &result).expect("Failed to write execute.txt");
+        return;
+    }
+
+    let mut results = Vec::new();
+    for header in pbo.files() {
+        let filename = header.filename().to_string();
+        let Some((id, ext)) = filename.split_once('.') else {
+            continue;
+        };
+        let (Ok(id), "sqf" | "sqfc") = (id.parse(), ext) else {
+            continue;
+        };
+        let content = read(&mut pbo, &filename).expect("Failed to read script");
+        println!("\"tab: benchmarking {id}\"");
+        directives(&path, &bootstrap, "setup.sqf", &setup);
+        directives(&path, &bootstrap, &filename, &content);
+        directives(&path, &bootstrap, "teardown.sqf", &teardown);
+        let samples = samples(0.001 * f64::from(id + 1), repetitions);
+        results.push(CompareResult {
+            id,
+            time: samples[0],
+            iter: cycles(&run),
+            ret: value(&with_arguments(&content, &run)),
+            logs: Vec::new(),
+            errors: Vec::new(),
+            samples,
+            mismatches: Vec::new(),
+            timing: Timing::default(),
+        });
+    }
+    println!("\"tab: end\"");
+    let out = File::create(path.join("compare.txt")).expect("Failed to create compare.txt");
+    serde_json::to_writer(out, &results).expect("Failed to write compare.txt");
+}
+
+const MATRIX_MARKER: &str = "diag_log \"tab: benchmarking ";
+
+/// Run the template once for each set of parameters, in the order the bootstrap declares them.
+fn matrix(
+    path: &Path,
+    bootstrap: &str,
+    run: &str,
+    template: &str,
+    setup: &str,
+    teardown: &str,
+    repetitions: u32,
+) {
+    let mut results = Vec::new();
+    for block in bootstrap.split(MATRIX_MARKER).skip(1) {
+        let (id, declarations) = block.split_once('"').expect("Invalid matrix block");
+        let id: u16 = id.parse().expect("Invalid matrix index");
+        let content = declarations
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("private "))
+            .filter_map(|line| line.strip_suffix(';')?.split_once(" = "))
+            .fold(template.to_string(), |content, (name, value)| {
+                content.replace(name, value)
+            });
+        println!("\"tab: benchmarking {id}\"");
+        directives(path, bootstrap, "setup.sqf", setup);
+        directives(path, bootstrap, "bench.sqf", &content);
+        directives(path, bootstrap, "teardown.sqf", teardown);
+        let samples = samples(0.001 * f64::from(id + 1), repetitions);
+        results.push(CompareResult {
+            id,
+            time: samples[0],
+            iter: cycles(run),
+            ret: value(&with_arguments(&content, run)),
+            logs: Vec::new(),
+            errors: Vec::new(),
+            samples,
+            mismatches: Vec::new(),
+            timing: Timing::default(),
+        });
+    }
+    println!("\"tab: end\"");
+    let out = File::create(path.join("compare.txt")).expect("Failed to create compare.txt");
+    serde_json::to_writer(out, &results).expect("Failed to write compare.txt");
+}
+
+fn read(pbo: &mut ReadablePbo<File>, name: &str) -> Option<String> {
+    let mut content = String::new();
+    pbo.file(name)
+        .expect("Failed to read PBO")?
+        .read_to_string(&mut content)
+        .expect("Failed to read file");
+    Some(content)
+}
+
+/// The most iterations run.sqf asks for, in either timing mode.
+fn cycles(run: &str) -> u32 {
+    argument(run, "_cycles < ")
+        .or_else(|| {
+            let (_, call) = run.split_once("diag_codePerformance [")?;
+            let (call, _) = call.split_once("];")?;
+            call.rsplit(", ").next()?.parse().ok()
+        })
+        .unwrap_or(10000)
+}
+
+/// `content` with `_this` replaced by the arguments run.sqf passes, if any.
+fn with_arguments(content: &str, run: &str) -> String {
+    run.split_once("private _ret = ")
+        .and_then(|(_, rest)| rest.split_once(" call _code"))
+        .map_or_else(
+            || content.to_string(),
+            |(arguments, _)| content.replace("_this", arguments),
+        )
+}
+
+fn samples(time: f64, repetitions: u32) -> Vec<f64> {
+    (0..repetitions)
+        .map(|i| 0.0001f64.mul_add(f64::from(i), time))
+        .collect()
+}
+
+fn value(content: &str) -> Value {
+    let content = content.trim();
+    content
+        .parse()
+        .map_or_else(|_| Value::String(content.to_string()), Value::Number)
+}
+
+/// The first script that asks to stop before the benchmark starts.
+fn never_starts(pbo: &mut ReadablePbo<File>) -> Option<String> {
+    pbo.files()
+        .iter()
+        .map(|header| header.filename().to_string())
+        .filter(|filename| filename != "bootstrap.sqf")
+        .collect::<Vec<_>>()
+        .into_iter()
+        .filter_map(|filename| read(pbo, &filename))
+        .find(|content| content.contains("fake:never-start"))
+}
+
+fn hang() -> ! {
+    loop {
+        std::thread::sleep(Duration::from_mins(1));
+    }
+}
+
+/// The number following a directive, if the script contains it.
+fn argument<T: std::str::FromStr>(content: &str, directive: &str) -> Option<T> {
+    let (_, rest) = content.split_once(directive)?;
+    let value = rest
+        .split(|c: char| !c.is_ascii_digit() && c != '-')
+        .next()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| panic!("Invalid {directive} directive"));
+    Some(value)
+}
+
+fn directives(path: &Path, bootstrap: &str, file: &str, content: &str) {
+    for (number, line) in (1..).zip(content.lines()) {
+        if let Some((_, text)) = line.split_once("fake:log=") {
+            println!("{text}");
+        }
+        if let Some((_, message)) = line.split_once("fake:error=") {
+            script_error(file, number, message);
+        }
+        if line.contains("fake:compile-error") {
+            script_error(file, number, "Missing ;");
+            std::fs::write(path.join("compile_error.txt"), file)
+                .expect("Failed to write compile_error.txt");
+            std::process::exit(0);
+        }
+    }
+    if let Some(ms) = argument(content, "fake:sleep=") {
+        std::thread::sleep(Duration::from_millis(ms));
+    }
+    if content.contains("fake:hang") {
+        hang();
+    }
+    if content.contains("fake:timeout") {
+        timeout(path, bootstrap);
+    }
+    if content.contains("fake:crash") {
+        std::process::abort();
+    }
+    if let Some(code) = argument(content, "fake:exit=") {
+        std::process::exit(code);
+    }
+    if content.contains("fake:no-result") {
+        std::process::exit(0);
+    }
+}
+
+/// Log an error the way Arma does.
+fn script_error(file: &str, line: u32, message: &str) {
+    println!(" 9:41:03 Error in expression <fake>");
+    println!(" 9:41:03   Error position: <fake>");
+    println!(" 9:41:03   Error {message}");
+    println!(" 9:41:03 File \\tab\\{file}..., line {line}");
+}
+
+/// Write `timeout.txt` with the time the bootstrap asked for, then exit.
+fn timeout(path: &Path, bootstrap: &str) -> ! {
+    let time = bootstrap
+        .split_once("[\"timeout\", [")
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .and_then(|(args, _)| args.rsplit(',').next())
+        .map_or("0", str::trim);
+    std::fs::write(path.join("timeout.txt"), time).expect("Failed to write timeout.txt");
+    std::process::exit(0);
+}
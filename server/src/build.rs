@@ -1,6 +1,6 @@
 use std::{io::Cursor, path::PathBuf};
 
-use arma_bench::Request;
+use arma_bench::{to_sqf, CompareRequest, MatrixRequest, Options, Request, Timing};
 use hemtt_pbo::WritablePbo;
 use uuid::Uuid;
 
@@ -8,13 +8,37 @@ pub struct BuiltRequest {
     pub path: PathBuf,
 }
 
+impl BuiltRequest {
+    /// Where launchers write the output of the process.
+    #[must_use]
+    pub fn output(&self) -> PathBuf {
+        self.path.join("output.log")
+    }
+}
+
 impl Drop for BuiltRequest {
     fn drop(&mut self) {
         std::fs::remove_dir_all(&self.path).expect("Failed to remove temp directory");
     }
 }
 
-pub fn build(request: &Request) -> BuiltRequest {
+/// How many times `diag_codePerformance` runs a script when not told otherwise.
+const DEFAULT_CYCLES: u32 = 10000;
+
+/// How many seconds a request may run when the client does not ask for a timeout.
+///
+/// `diag_codePerformance` stops after a second, so each extra repetition adds one.
+pub fn default_timeout(request: &Request, repetitions: u32) -> u64 {
+    let extra = u64::from(repetitions.saturating_sub(1));
+    match request {
+        Request::Execute(_) => 30 + extra,
+        Request::Compare(files) => 120 + extra * files.len() as u64,
+        Request::Matrix(matrix) => 120 + extra * matrix.parameters.len() as u64,
+    }
+}
+
+/// Build the addon for a request, stopping it in-game after `timeout` seconds.
+pub fn build(request: &Request, timeout: u64, options: &Options) -> BuiltRequest {
     let id = Uuid::new_v4().to_string();
     let path = std::env::temp_dir()
         .join("arma_bench")
@@ -46,63 +70,237 @@ pub fn build(request: &Request) -> BuiltRequest {
     pbo.add_property("prefix", "tab");
     pbo.add_file("config.cpp", Cursor::new(config.as_bytes()))
         .expect("Failed to add config.cpp");
-    match request {
+    let bootstrap = match request {
         Request::Execute(content) => {
-            let bootstrap = format!(
-                r#"
-            diag_log "creating timeout";
-            "tab" callExtension ["timeout", ["{id}", 30]];
-            diag_log "starting benchmark";
-            private _code = compile preprocessFileLineNumbers "\tab\bench.sqf";
-            private _out = diag_codePerformance [_code];
-            private _ret = call _code;
-            diag_log "benchmark complete, saving results";
-            "tab" callExtension ["execute", ["{id}", _out, _ret]];
-            diag_log "dying";
-            "tab" callExtension ["die", []];
-            "#
-            );
-            pbo.add_file("bootstrap.sqf", Cursor::new(bootstrap.as_bytes()))
-                .expect("Failed to add bootstrap.sqf");
             pbo.add_file("bench.sqf", Cursor::new(content.as_bytes()))
                 .expect("Failed to add bench.sqf");
-            pbo.write(&mut file, true).expect("Failed to write PBO");
+            execute_bootstrap(&id, timeout, options)
         }
         Request::Compare(files) => {
-            let mut ids = Vec::new();
             for file in files {
-                ids.push(file.id.to_string());
                 let filename = format!("{}.{}", file.id, if file.sqfc { "sqfc" } else { "sqf" });
                 pbo.add_file(&filename, Cursor::new(&file.content))
                     .expect("Failed to add file");
             }
-            let bootstrap = format!(
-                r#"
+            compare_bootstrap(&id, timeout, options, files)
+        }
+        Request::Matrix(matrix) => {
+            pbo.add_file("bench.sqf", Cursor::new(matrix.template.as_bytes()))
+                .expect("Failed to add bench.sqf");
+            matrix_bootstrap(&id, timeout, options, matrix)
+        }
+    };
+    for (filename, content) in [
+        ("setup.sqf", &options.setup),
+        ("teardown.sqf", &options.teardown),
+    ] {
+        if let Some(content) = content {
+            pbo.add_file(filename, Cursor::new(content.as_bytes()))
+                .expect("Failed to add file");
+        }
+    }
+    let run = run(options);
+    pbo.add_file("run.sqf", Cursor::new(run.as_bytes()))
+        .expect("Failed to add run.sqf");
+    pbo.add_file("bootstrap.sqf", Cursor::new(bootstrap.as_bytes()))
+        .expect("Failed to add bootstrap.sqf");
+    pbo.write(&mut file, true).expect("Failed to write PBO");
+    BuiltRequest {
+        path: path.parent().expect("Failed to get parent").to_path_buf(),
+    }
+}
+
+/// Benchmarks `_code`, returning the samples and what it returned.
+///
+/// Compiled at runtime once the scripts it includes are known to compile,
+/// setup is included rather than called so the variables it declares stay in scope.
+fn run(options: &Options) -> String {
+    let include = |file: &str, content: &Option<String>| {
+        // The semicolon ends a last statement without one.
+        content
+            .as_ref()
+            .map(|_| format!("#include \"{file}\"\n;\n"))
+            .unwrap_or_default()
+    };
+    let arguments = options
+        .arguments
+        .as_ref()
+        .map(|arguments| to_sqf(arguments).expect("Arguments are checked on submit"));
+    let cycles = options.cycles.unwrap_or(DEFAULT_CYCLES);
+    let sample = match options.timing.unwrap_or_default() {
+        Timing::CodePerformance => match (&arguments, options.cycles) {
+            (None, None) => "_samples pushBack diag_codePerformance [_code];".to_string(),
+            (arguments, _) => format!(
+                "_samples pushBack diag_codePerformance [_code, {}, {cycles}];",
+                arguments.as_deref().unwrap_or("[]")
+            ),
+        },
+        // Stops after a second like diag_codePerformance, so the default timeout still fits.
+        Timing::TickTime => format!(
+            r"private _cycles = 0;
+    private _start = diag_tickTime;
+    while {{_cycles < {cycles} && {{diag_tickTime - _start < 1}}}} do {{
+        {call};
+        _cycles = _cycles + 1;
+    }};
+    _samples pushBack [(diag_tickTime - _start) * 1000 / _cycles, _cycles];",
+            call = call(arguments.as_deref()),
+        ),
+    };
+    format!(
+        r#"{setup}private _samples = [];
+for "_i" from 1 to _repetitions do {{
+    {sample}
+}};
+private _ret = {call};
+{teardown}[_samples, _ret]
+"#,
+        setup = include("setup.sqf", &options.setup),
+        call = call(arguments.as_deref()),
+        teardown = include("teardown.sqf", &options.teardown),
+    )
+}
+
+/// Calls `_code`, with `arguments` as `_this` if there are any.
+fn call(arguments: Option<&str>) -> String {
+    arguments.map_or_else(
+        || "call _code".to_string(),
+        |arguments| format!("{arguments} call _code"),
+    )
+}
+
+/// The start of every bootstrap, up to where the benchmark begins.
+///
+/// Defines `_compile`, which reports a file that fails to compile and exits.
+fn prelude(id: &str, timeout: u64, options: &Options) -> String {
+    let repetitions = options.repetitions.unwrap_or(1);
+    let setup = if options.setup.is_some() {
+        r#"["setup.sqf"] call _compile;"#
+    } else {
+        ""
+    };
+    let teardown = if options.teardown.is_some() {
+        r#"["teardown.sqf"] call _compile;"#
+    } else {
+        ""
+    };
+    format!(
+        r#"
+            "tab" callExtension ["started", ["{id}"]];
             diag_log "creating timeout";
-            "tab" callExtension ["timeout", ["{id}", 120]];
+            "tab" callExtension ["timeout", ["{id}", {timeout}]];
             diag_log "starting benchmark";
+            private _repetitions = {repetitions};
+            diag_log "tab: begin";
+            private _compile = {{
+                params ["_file"];
+                private _source = preprocessFileLineNumbers ("\tab\" + _file);
+                private _code = compile _source;
+                if (str _code == "{{}}" && {{trim _source != ""}}) then {{
+                    "tab" callExtension ["compile_error", ["{id}", _file]];
+                }};
+                _code
+            }};
+            {setup}
+            {teardown}
+            private _run = ["run.sqf"] call _compile;
+            "#
+    )
+}
+
+fn execute_bootstrap(id: &str, timeout: u64, options: &Options) -> String {
+    let prelude = prelude(id, timeout, options);
+    format!(
+        r#"{prelude}
+            private _code = ["bench.sqf"] call _compile;
+            (call _run) params ["_samples", "_ret"];
+            diag_log "tab: end";
+            diag_log "benchmark complete, saving results";
+            "tab" callExtension ["execute", ["{id}", _samples, _ret]];
+            diag_log "dying";
+            "tab" callExtension ["die", []];
+            "#
+    )
+}
+
+fn compare_bootstrap(
+    id: &str,
+    timeout: u64,
+    options: &Options,
+    files: &[CompareRequest],
+) -> String {
+    let prelude = prelude(id, timeout, options);
+    let ids = files
+        .iter()
+        .map(|file| format!("\"{}\"", file.id))
+        .collect::<Vec<_>>();
+    // sqfc is already compiled, only sources can fail here
+    let sources = files
+        .iter()
+        .filter(|file| !file.sqfc)
+        .map(|file| format!("\"{}\"", file.id))
+        .collect::<Vec<_>>();
+    format!(
+        r#"{prelude}
             private _out = [];
             {{
-                private _code = compileScript [format["\tab\%1.sqf", _x]];
-                private _ret = [_x];
-                diag_log format["benchmarking %1", _x];
-                _ret pushBack diag_codePerformance [_code];
-                _ret pushBack call _code;
-                _out pushBack _ret;
-            }} forEach ["{}"];
+                diag_log format["tab: benchmarking %1", _x];
+                private _code = if (_x in [{sources}]) then {{
+                    [format["%1.sqf", _x]] call _compile
+                }} else {{
+                    compileScript [format["\tab\%1.sqf", _x]]
+                }};
+                _out pushBack ([_x] + call _run);
+            }} forEach [{ids}];
+            diag_log "tab: end";
             diag_log "benchmark complete, saving results";
             "tab" callExtension ["compare", ["{id}", _out]];
             diag_log "dying";
             "tab" callExtension ["die", []];
             "#,
-                ids.join("\", \"")
-            );
-            pbo.add_file("bootstrap.sqf", Cursor::new(bootstrap.as_bytes()))
-                .expect("Failed to add bootstrap.sqf");
-            pbo.write(&mut file, true).expect("Failed to write PBO");
-        }
-    }
-    BuiltRequest {
-        path: path.parent().expect("Failed to get parent").to_path_buf(),
-    }
+        ids = ids.join(", "),
+        sources = sources.join(", "),
+    )
+}
+
+/// Declares each set of parameters in its own scope, then benchmarks it like a compared script
+/// with the index of the set as its ID.
+fn matrix_bootstrap(id: &str, timeout: u64, options: &Options, matrix: &MatrixRequest) -> String {
+    let prelude = prelude(id, timeout, options);
+    let sets = matrix
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(index, parameters)| {
+            let declarations = parameters
+                .iter()
+                .map(|(name, value)| {
+                    let value = to_sqf(value).expect("Parameters are checked on submit");
+                    format!("private {name} = {value};")
+                })
+                .collect::<Vec<_>>();
+            format!(
+                r#"
+            diag_log "tab: benchmarking {index}";
+            call {{
+                {declarations}
+                _out pushBack (["{index}"] + call _run);
+            }};"#,
+                declarations = declarations.join("\n                "),
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        r#"{prelude}
+            private _code = ["bench.sqf"] call _compile;
+            private _out = [];
+            {sets}
+            diag_log "tab: end";
+            diag_log "benchmark complete, saving results";
+            "tab" callExtension ["compare", ["{id}", _out]];
+            diag_log "dying";
+            "tab" callExtension ["die", []];
+            "#,
+        sets = sets.concat(),
+    )
 }
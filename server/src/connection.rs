@@ -0,0 +1,445 @@
+use std::{collections::HashMap, io::ErrorKind, sync::Arc};
+
+use arma_bench::{
+    invalid_parameters, to_sqf, AssertEqual, ClientHello, Command, Error, MatrixRequest, Message,
+    Options, Progress, Reply, Request, RequestId, Response, ServerConfig, ServerHello,
+    CAPABILITY_PROGRESS, HEADER_ID, LEGACY_HEADER_ID,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    sync::{mpsc, oneshot},
+};
+use tracing::{debug, error, info, info_span, trace, warn, Instrument};
+
+use crate::{build, history::History, queue::Queue, InternalRequest, RequestHandle, Settings};
+
+type Reader = BufReader<OwnedReadHalf>;
+type Writer = BufWriter<OwnedWriteHalf>;
+
+pub async fn process(
+    socket: TcpStream,
+    queue: Arc<Queue>,
+    settings: Settings,
+    history: Option<Arc<History>>,
+) {
+    let addr = match socket.peer_addr() {
+        Ok(addr) => addr,
+        Err(e) => {
+            warn!(error = %e, "Failed to get peer address");
+            return;
+        }
+    };
+    async move {
+        trace!("Connection received");
+        match session(socket, &queue, &settings, history.as_ref()).await {
+            Ok(()) => info!("Disconnected"),
+            Err(Error::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                info!("Disconnected mid-message");
+            }
+            Err(e) => warn!(error = %e, "Connection closed"),
+        }
+    }
+    .instrument(info_span!("client", %addr))
+    .await;
+}
+
+/// A request submitted on this connection that has not been answered yet.
+struct InFlight {
+    ticket: u64,
+    /// Stops the request once a worker has it, dropping it does the same.
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+enum Event {
+    Progress(Progress),
+    Done(Response),
+}
+
+type Events = mpsc::UnboundedSender<(RequestId, Event)>;
+
+/// Pass a request's progress and response on to the connection, tagged with its ID.
+async fn forward(
+    id: RequestId,
+    mut response: oneshot::Receiver<Response>,
+    mut progress: mpsc::UnboundedReceiver<Progress>,
+    events: Events,
+) {
+    loop {
+        let event = tokio::select! {
+            // Progress is always sent before the response, drain it first.
+            biased;
+            Some(progress) = progress.recv() => Event::Progress(progress),
+            response = &mut response => Event::Done(response.unwrap_or_else(|_| {
+                error!(id, "Request was dropped without a response");
+                Response::Error("request was dropped by the server".to_string())
+            })),
+        };
+        let done = matches!(event, Event::Done(_));
+        // The connection may have closed, nobody is listening then.
+        if events.send((id, event)).is_err() || done {
+            return;
+        }
+    }
+}
+
+/// Write a response, tagged with its request.
+async fn reply(write: &mut Writer, id: Option<RequestId>, response: Response) -> Result<(), Error> {
+    Reply { id, response }.write_async(write).await
+}
+
+async fn session(
+    socket: TcpStream,
+    queue: &Queue,
+    settings: &Settings,
+    history: Option<&Arc<History>>,
+) -> Result<(), Error> {
+    let (read, write) = socket.into_split();
+    let mut read = BufReader::new(read);
+    let mut write = BufWriter::new(write);
+    // Read the client's header ID first, older clients expect their own back.
+    let mut buf = [0; 16];
+    read.read_exact(&mut buf).await?;
+    if buf == *LEGACY_HEADER_ID {
+        return refuse_legacy(&mut read, &mut write, settings).await;
+    }
+    // Write the header ID to the client.
+    write.write_all(HEADER_ID).await?;
+    write.flush().await?;
+    if buf != *HEADER_ID {
+        return Err(Error::HandshakeMismatch);
+    }
+
+    let hello =
+        ClientHello::from_async_reader_with_limit(&mut read, settings.max_message_size).await?;
+    let reply_hello = ServerHello::negotiate(&hello, &settings.protocol_versions);
+    reply_hello.write_async(&mut write).await?;
+    let protocol = match reply_hello {
+        ServerHello::Accepted(protocol) => protocol,
+        ServerHello::UnsupportedVersion { .. } => {
+            warn!(
+                min_version = hello.min_version,
+                max_version = hello.max_version,
+                "Unsupported protocol version"
+            );
+            return Ok(());
+        }
+    };
+    // The client has successfully connected.
+    info!(
+        version = protocol.version,
+        capabilities = ?protocol.capabilities,
+        "Connected"
+    );
+
+    let server_config =
+        ServerConfig::from_async_reader_with_limit(&mut read, settings.max_message_size).await?;
+    debug!(config = ?server_config, "Received server config");
+
+    // Send wait packet to client
+    write.write_all(&[1]).await?;
+    write.flush().await?;
+
+    // Commands are read on their own task, so a cancel can arrive while waiting on a response.
+    let (commands_tx, mut commands) = mpsc::channel(16);
+    let reader =
+        tokio::spawn(read_commands(read, settings.max_message_size, commands_tx).in_current_span());
+
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let mut in_flight = HashMap::new();
+    let result = async {
+        loop {
+            tokio::select! {
+                Some((id, event)) = events.recv() => match event {
+                    Event::Progress(progress) => {
+                        trace!(id, ?progress, "Progress");
+                        if protocol.supports(CAPABILITY_PROGRESS) {
+                            reply(&mut write, Some(id), Response::Progress(progress))
+                                .await?;
+                        }
+                    }
+                    Event::Done(response) => {
+                        in_flight.remove(&id);
+                        debug!(id, ?response, "Sending response");
+                        reply(&mut write, Some(id), response).await?;
+                    }
+                },
+                command = commands.recv() => match command {
+                    // A client closing the connection between messages is a clean disconnect.
+                    None => return Ok(()),
+                    Some(Ok(command)) => {
+                        let session = Session {
+                            settings,
+                            config: &server_config,
+                            queue,
+                            history,
+                            events: &events_tx,
+                        };
+                        session
+                            .handle_command(command, &mut in_flight, &mut write)
+                            .await?;
+                    }
+                    Some(Err(Error::Decode(e))) => {
+                        // The whole frame was consumed, so the connection is still usable.
+                        warn!(error = %e, "Received invalid request");
+                        let response = Response::Error(format!("invalid request: {e}"));
+                        reply(&mut write, None, response).await?;
+                    }
+                    Some(Err(e @ Error::MessageTooLarge { .. })) => {
+                        // The payload can not be skipped safely, so the connection has to close.
+                        reply(&mut write, None, Response::Error(e.to_string())).await?;
+                        return Err(e);
+                    }
+                    Some(Err(e)) => return Err(e),
+                },
+            }
+        }
+    }
+    .await;
+
+    reader.abort();
+    // Nobody is waiting for the requests anymore, don't let them take up a worker.
+    // Dropping the cancel senders stops any that are already running.
+    for (id, in_flight) in in_flight {
+        if queue.remove(in_flight.ticket).is_some() {
+            debug!(id, "Removed abandoned request from the queue");
+        }
+    }
+    result
+}
+
+/// Turn away a client from before the handshake, which speaks protocol version 1.
+///
+/// It is answered in its own protocol up to its first request,
+/// so the request fails with why instead of the header ID not matching.
+async fn refuse_legacy(
+    read: &mut Reader,
+    write: &mut Writer,
+    settings: &Settings,
+) -> Result<(), Error> {
+    write.write_all(LEGACY_HEADER_ID).await?;
+    write.flush().await?;
+    ServerConfig::from_async_reader_with_limit(read, settings.max_message_size).await?;
+    write.write_all(&[1]).await?;
+    write.flush().await?;
+    // Only answered, so a request this server can not decode is fine.
+    match Request::from_async_reader_with_limit(read, settings.max_message_size).await {
+        Ok(_) | Err(Error::Decode(_)) => {}
+        Err(e) => return Err(e),
+    }
+    let versions = &settings.protocol_versions;
+    let response = Response::Error(format!(
+        "this client speaks protocol version 1, the server only supports versions {} to {}, \
+        update arma-bench to connect",
+        versions.start(),
+        versions.end()
+    ));
+    // Version 1 responses are not tagged with a request.
+    response.write_async(write).await?;
+    warn!("Refused a protocol version 1 client");
+    Ok(())
+}
+
+/// What a command needs to know about its connection.
+struct Session<'a> {
+    settings: &'a Settings,
+    config: &'a ServerConfig,
+    queue: &'a Queue,
+    history: Option<&'a Arc<History>>,
+    events: &'a Events,
+}
+
+impl Session<'_> {
+    async fn handle_command(
+        &self,
+        command: Command,
+        in_flight: &mut HashMap<RequestId, InFlight>,
+        write: &mut Writer,
+    ) -> Result<(), Error> {
+        match command {
+            Command::Submit {
+                id,
+                request,
+                options,
+            } => {
+                debug!(id, ?request, ?options, "Received request");
+                if in_flight.contains_key(&id) {
+                    let response = Response::Error(format!("request {id} is already in flight"));
+                    return reply(write, Some(id), response).await;
+                }
+                if let Some(error) = invalid_request(&request, &options, self.settings.max_boots) {
+                    return reply(write, Some(id), Response::Error(error)).await;
+                }
+                let max_timeout = self.settings.max_timeout;
+                let timeout = match options.timeout {
+                    Some(timeout) if timeout == 0 || timeout > max_timeout => {
+                        let response = Response::Error(format!(
+                            "timeout must be between 1 and {max_timeout} seconds"
+                        ));
+                        return reply(write, Some(id), response).await;
+                    }
+                    Some(timeout) => timeout,
+                    None => build::default_timeout(&request, options.repetitions.unwrap_or(1))
+                        .min(max_timeout),
+                };
+                let (callback, response) = oneshot::channel();
+                let (progress, progress_rx) = mpsc::unbounded_channel();
+                let (cancel, cancel_rx) = oneshot::channel();
+                let ticket = self.queue.push(RequestHandle {
+                    callback,
+                    progress,
+                    cancel: cancel_rx,
+                    request: InternalRequest {
+                        config: self.config.clone(),
+                        request,
+                        timeout,
+                        options,
+                    },
+                });
+                tokio::spawn(
+                    forward(id, response, progress_rx, self.events.clone()).in_current_span(),
+                );
+                in_flight.insert(
+                    id,
+                    InFlight {
+                        ticket,
+                        cancel: Some(cancel),
+                    },
+                );
+            }
+            Command::Cancel(id) => {
+                let Some(current) = in_flight.get_mut(&id) else {
+                    // It may have finished while the cancel was on its way.
+                    debug!(id, "Nothing to cancel");
+                    return Ok(());
+                };
+                if let Some(handle) = self.queue.remove(current.ticket) {
+                    debug!(id, "Cancelled while queued");
+                    let _ = handle.callback.send(Response::Cancelled);
+                } else if let Some(cancel) = current.cancel.take() {
+                    debug!(id, "Cancelling running request");
+                    let _ = cancel.send(());
+                }
+            }
+            Command::History { id, query } => {
+                debug!(id, ?query, "Received history query");
+                let Some(history) = self.history.cloned() else {
+                    let response =
+                        Response::History(Err("this server does not keep history".to_string()));
+                    return reply(write, Some(id), response).await;
+                };
+                // Answered like a request, so reading the database does not hold up the connection.
+                let events = self.events.clone();
+                tokio::spawn(
+                    async move {
+                        let records =
+                            tokio::task::spawn_blocking(move || history.query(&query)).await;
+                        let response = match records {
+                            Ok(Ok(records)) => Response::History(Ok(records)),
+                            Ok(Err(e)) => {
+                                error!(error = %e, "Failed to read history");
+                                Response::History(Err(format!("failed to read history: {e}")))
+                            }
+                            Err(e) => {
+                                error!(error = %e, "History task failed");
+                                Response::History(Err("failed to read history".to_string()))
+                            }
+                        };
+                        let _ = events.send((id, Event::Done(response)));
+                    }
+                    .in_current_span(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why `request` can not run with `options`, if it can't.
+fn invalid_request(request: &Request, options: &Options, max_boots: u32) -> Option<String> {
+    if options.repetitions == Some(0) {
+        return Some("repetitions must be at least 1".to_string());
+    }
+    if options.cycles == Some(0) {
+        return Some("cycles must be at least 1".to_string());
+    }
+    if options
+        .arguments
+        .as_ref()
+        .is_some_and(|arguments| to_sqf(arguments).is_none())
+    {
+        return Some("arguments must only contain finite numbers".to_string());
+    }
+    if let Request::Matrix(matrix) = request {
+        if let Some(error) = invalid_matrix(matrix) {
+            return Some(error);
+        }
+    }
+    if let Some(error) = options
+        .assert_equal
+        .as_ref()
+        .and_then(|assert| invalid_assertion(request, assert))
+    {
+        return Some(error);
+    }
+    if options
+        .boots
+        .is_some_and(|boots| boots == 0 || boots > max_boots)
+    {
+        return Some(format!("boots must be between 1 and {max_boots}"));
+    }
+    None
+}
+
+/// Why `assert` can not be checked for `request`, if it can't.
+fn invalid_assertion(request: &Request, assert: &AssertEqual) -> Option<String> {
+    let Request::Compare(files) = request else {
+        return Some("results can only be asserted equal when comparing".to_string());
+    };
+    if !files.iter().any(|file| file.id == assert.baseline) {
+        return Some(format!(
+            "baseline {} is not one of the compared scripts",
+            assert.baseline
+        ));
+    }
+    if !(assert.tolerance >= 0.0 && assert.tolerance.is_finite()) {
+        return Some("tolerance must be a positive number".to_string());
+    }
+    None
+}
+
+/// Why `matrix` can not be benchmarked, if it can't.
+fn invalid_matrix(matrix: &MatrixRequest) -> Option<String> {
+    if matrix.parameters.is_empty() || matrix.parameters.len() > usize::from(u16::MAX) {
+        return Some(format!(
+            "a matrix needs between 1 and {} sets of parameters",
+            u16::MAX
+        ));
+    }
+    invalid_parameters(&matrix.parameters)
+}
+
+async fn read_commands(
+    mut read: Reader,
+    max_message_size: u64,
+    commands: mpsc::Sender<Result<Command, Error>>,
+) {
+    loop {
+        match read.fill_buf().await {
+            Ok([]) => return,
+            Ok(_) => {}
+            Err(e) => {
+                let _ = commands.send(Err(e.into())).await;
+                return;
+            }
+        }
+        let command = Command::from_async_reader_with_limit(&mut read, max_message_size).await;
+        let fatal = matches!(&command, Err(e) if !matches!(e, Error::Decode(_)));
+        if commands.send(command).await.is_err() || fatal {
+            return;
+        }
+    }
+}
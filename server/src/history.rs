@@ -0,0 +1,320 @@
+use std::{
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use arma_bench::{
+    script_hash, Error, ExecuteResult, HistoryQuery, HistoryRecord, Options, Parameters, Request,
+    Response, ServerConfig,
+};
+use arma_rs::Value;
+use rusqlite::{params, Connection};
+
+/// The most records one query returns.
+pub const MAX_RECORDS: u32 = 10_000;
+
+/// Roughly how large the records of one query may be, in bytes, half the client's default
+/// message limit. Results and logs can be large, so a query may stop short of its limit.
+const MAX_QUERY_SIZE: usize = 32 * 1024 * 1024;
+
+/// Every script the server benchmarked, kept in a database.
+pub struct History {
+    connection: Mutex<Connection>,
+}
+
+impl History {
+    /// Open the database at `path`, creating it if it does not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the database can not be opened or created.
+    pub fn open(path: &Path) -> Result<Self, rusqlite::Error> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                time INTEGER NOT NULL,
+                hash TEXT NOT NULL,
+                binary TEXT NOT NULL,
+                branch TEXT NOT NULL,
+                version TEXT,
+                parameters TEXT NOT NULL,
+                options TEXT NOT NULL,
+                failure TEXT,
+                iterations INTEGER NOT NULL,
+                timing TEXT NOT NULL,
+                samples TEXT NOT NULL,
+                ret TEXT NOT NULL,
+                logs TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS runs_hash ON runs (hash, time);
+            CREATE INDEX IF NOT EXISTS runs_time ON runs (time);",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Keep every script of a finished request, with why it failed if it did.
+    /// Cancelled requests are not kept.
+    ///
+    /// # Errors
+    /// Returns an error if the records can not be written.
+    pub fn record(
+        &self,
+        config: &ServerConfig,
+        version: Option<&str>,
+        request: &Request,
+        options: &Options,
+        response: &Response,
+    ) -> Result<(), rusqlite::Error> {
+        let Some(runs) = runs(request, response) else {
+            return Ok(());
+        };
+        // Runs are only comparable when these match, the rest do not change what is measured.
+        let options = Options {
+            timeout: None,
+            skip_logs: false,
+            assert_equal: None,
+            ..options.clone()
+        };
+        let time = now();
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let transaction = connection.transaction()?;
+        for (hash, parameters, result) in runs {
+            let (failure, result) = match result {
+                // Kept like the statistics summarise it, a result without samples has its one time.
+                Ok(result) if result.samples.is_empty() => (
+                    None,
+                    ExecuteResult {
+                        samples: vec![result.time],
+                        ..result
+                    },
+                ),
+                Ok(result) => (None, result),
+                Err((failure, logs)) => (
+                    Some(failure),
+                    ExecuteResult {
+                        time: 0.0,
+                        iter: 0,
+                        ret: Value::Null,
+                        logs,
+                        errors: Vec::new(),
+                        samples: Vec::new(),
+                        timing: options.timing.unwrap_or_default(),
+                    },
+                ),
+            };
+            transaction.execute(
+                "INSERT INTO runs
+                    (time, hash, binary, branch, version, parameters, options, failure, iterations,
+                        timing, samples, ret, logs)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    time,
+                    hash,
+                    config.binary,
+                    config.branch,
+                    version,
+                    json(&parameters),
+                    json(&options),
+                    failure,
+                    result.iter,
+                    json(&result.timing),
+                    json(&result.samples),
+                    json(&result.ret),
+                    json(&result.logs),
+                ],
+            )?;
+        }
+        let committed = transaction.commit();
+        drop(connection);
+        committed
+    }
+
+    /// The records matching `query`, oldest first.
+    ///
+    /// The newest are kept when there are more than the limit,
+    /// or they would be larger than [`MAX_QUERY_SIZE`].
+    ///
+    /// # Errors
+    /// Returns an error if the database can not be read.
+    pub fn query(&self, query: &HistoryQuery) -> Result<Vec<HistoryRecord>, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // The newest records within the limit, put back in order below.
+        let mut statement = connection.prepare_cached(
+            "SELECT id, time, hash, binary, branch, version, parameters, options, failure,
+                    iterations, timing, samples, ret, CASE WHEN ?5 THEN logs ELSE '[]' END
+                FROM runs
+                WHERE (?1 IS NULL OR hash = ?1)
+                    AND (?2 IS NULL OR time >= ?2)
+                    AND (?3 IS NULL OR time < ?3)
+                ORDER BY id DESC
+                LIMIT ?4",
+        )?;
+        let limit = query
+            .limit
+            .map_or(MAX_RECORDS, |limit| limit.min(MAX_RECORDS));
+        let rows = statement.query_map(
+            params![query.hash, query.since, query.until, limit, query.logs],
+            |row| {
+                // The JSON and failure columns, which are most of a record.
+                let size = (6..=13)
+                    .map(|column| {
+                        row.get_ref(column)
+                            .ok()
+                            .and_then(|value| value.as_str().ok())
+                            .map_or(0, str::len)
+                    })
+                    .sum::<usize>();
+                let record = HistoryRecord {
+                    id: row.get(0)?,
+                    time: row.get(1)?,
+                    hash: row.get(2)?,
+                    config: ServerConfig {
+                        binary: row.get(3)?,
+                        branch: row.get(4)?,
+                        branch_password: String::new(),
+                    },
+                    version: row.get(5)?,
+                    parameters: parse(row, 6)?,
+                    options: parse(row, 7)?,
+                    failure: row.get(8)?,
+                    iterations: row.get(9)?,
+                    timing: parse(row, 10)?,
+                    samples: parse(row, 11)?,
+                    ret: parse(row, 12)?,
+                    logs: parse(row, 13)?,
+                };
+                Ok((record, size))
+            },
+        )?;
+        let mut records = Vec::new();
+        let mut total = 0;
+        for row in rows {
+            let (record, size) = row?;
+            total += size;
+            if total > MAX_QUERY_SIZE && !records.is_empty() {
+                break;
+            }
+            records.push(record);
+        }
+        drop(statement);
+        drop(connection);
+        records.reverse();
+        Ok(records)
+    }
+}
+
+/// A script a request ran, with its result or why it failed and what Arma logged.
+type Run = (
+    String,
+    Parameters,
+    Result<ExecuteResult, (String, Vec<String>)>,
+);
+
+/// Every script `response` answers for, `None` if it is not kept.
+fn runs(request: &Request, response: &Response) -> Option<Vec<Run>> {
+    let runs = match (request, response) {
+        (Request::Execute(content), Response::Execute(Ok(result))) => {
+            vec![(
+                script_hash(content.as_bytes()),
+                Parameters::new(),
+                Ok(result.clone()),
+            )]
+        }
+        (Request::Compare(files), Response::Compare(Ok(results))) => results
+            .iter()
+            .filter_map(|result| {
+                let file = files.iter().find(|file| file.id == result.id)?;
+                Some((
+                    script_hash(&file.content),
+                    Parameters::new(),
+                    Ok(ExecuteResult {
+                        time: result.time,
+                        iter: result.iter,
+                        ret: result.ret.clone(),
+                        logs: result.logs.clone(),
+                        errors: result.errors.clone(),
+                        samples: result.samples.clone(),
+                        timing: result.timing,
+                    }),
+                ))
+            })
+            .collect(),
+        (Request::Matrix(matrix), Response::Matrix(Ok(results))) => {
+            let hash = script_hash(matrix.template.as_bytes());
+            results
+                .iter()
+                .map(|result| {
+                    (
+                        hash.clone(),
+                        result.parameters.clone(),
+                        Ok(result.result.clone()),
+                    )
+                })
+                .collect()
+        }
+        (_, Response::Cancelled | Response::Progress(_)) => return None,
+        (request, failure) => {
+            let error = match failure.clone() {
+                Response::Execute(Err(e))
+                | Response::Compare(Err(e))
+                | Response::Matrix(Err(e)) => Error::ServerError(e),
+                failure => failure.into_error(),
+            };
+            let failure = (error.to_string(), error.logs().to_vec());
+            scripts(request)
+                .into_iter()
+                .map(|(hash, parameters)| (hash, parameters, Err(failure.clone())))
+                .collect()
+        }
+    };
+    Some(runs)
+}
+
+/// The hash and parameters of every script `request` benchmarks.
+fn scripts(request: &Request) -> Vec<(String, Parameters)> {
+    match request {
+        Request::Execute(content) => vec![(script_hash(content.as_bytes()), Parameters::new())],
+        Request::Compare(files) => files
+            .iter()
+            .map(|file| (script_hash(&file.content), Parameters::new()))
+            .collect(),
+        Request::Matrix(matrix) => {
+            let hash = script_hash(matrix.template.as_bytes());
+            matrix
+                .parameters
+                .iter()
+                .map(|parameters| (hash.clone(), parameters.clone()))
+                .collect()
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Columns that hold more than a number or text are stored as JSON.
+fn json(value: &impl serde::Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn parse<T: serde::de::DeserializeOwned>(
+    row: &rusqlite::Row<'_>,
+    column: usize,
+) -> Result<T, rusqlite::Error> {
+    let text: String = row.get(column)?;
+    serde_json::from_str(&text).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
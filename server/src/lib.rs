@@ -1,166 +1,453 @@
-use arma_bench::{Message, Request, Response, ServerConfig, HEADER_ID};
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
-    net::{TcpListener, TcpStream},
+use std::{
+    io::ErrorKind,
+    os::unix::process::ExitStatusExt,
+    process::ExitStatus,
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
-use tracing::{debug, error, info, trace};
+
+use arma_bench::{
+    mismatches, AssertEqual, CompareResult, ExecuteResult, MatrixResult, Options, Progress,
+    Request, Response, ServerConfig, Timing,
+};
+use tokio::{net::TcpListener, sync::oneshot};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 mod arma;
 mod build;
+mod connection;
+mod history;
+mod logs;
+mod queue;
 mod server;
+mod settings;
+mod worker;
+
+pub use arma::{Arma, Install, Installer, Launched, Launcher, SteamCmd};
+pub use build::BuiltRequest;
+use history::History;
+use logs::Logs;
+use queue::Queue;
+pub use settings::Settings;
+pub use worker::Worker;
 
 #[derive(Debug)]
 pub struct InternalRequest {
     config: ServerConfig,
     request: Request,
+    /// Seconds the request may run, already checked against [`Settings::max_timeout`].
+    timeout: u64,
+    options: Options,
 }
 
 #[derive(Debug)]
 pub struct RequestHandle {
-    callback: tokio::sync::oneshot::Sender<Response>,
+    callback: oneshot::Sender<Response>,
+    progress: ProgressSender,
+    /// Fires, or is dropped, when the client no longer wants the request.
+    cancel: oneshot::Receiver<()>,
     request: InternalRequest,
 }
 
-/// Start the server.
+impl RequestHandle {
+    fn report(&self, progress: Progress) {
+        // The client may have disconnected, it will not care about progress then.
+        let _ = self.progress.send(progress);
+    }
+}
+
+/// Reports the [`Progress`] of a request back to its connection.
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<Progress>;
+
+/// Start the server, running requests with `launcher`.
 ///
 /// # Panics
-/// Panics on I/O errors.
-pub async fn server(addr: String) {
+/// Panics if the address cannot be bound.
+pub async fn server<L: Launcher>(addr: String, settings: Settings, launcher: L) {
     info!("Starting on {}", addr);
     let listener = TcpListener::bind(addr).await.expect("Failed to bind");
 
-    let (request_sender, mut request_receiver) = tokio::sync::mpsc::channel(16);
-
-    tokio::spawn(async move {
-        loop {
-            match request_receiver.recv().await {
-                Some(request) => {
-                    handle(request).await;
-                }
-                None => {
-                    error!("Failed to receive request");
+    let queue = Arc::new(Queue::default());
+    let launcher = Arc::new(launcher);
+    let history = settings
+        .history
+        .as_ref()
+        .and_then(|path| match History::open(path) {
+            Ok(history) => {
+                info!(path = %path.display(), "Keeping history");
+                Some(Arc::new(history))
+            }
+            Err(e) => {
+                error!(path = %path.display(), error = %e, "Failed to open history, keeping none");
+                None
+            }
+        });
+    info!(workers = settings.workers, "Starting workers");
+    for id in 0..settings.workers {
+        let worker = Worker::new(id, &settings);
+        let queue = queue.clone();
+        let launcher = launcher.clone();
+        let history = history.clone();
+        let grace = settings.watchdog_grace;
+        tokio::spawn(
+            async move {
+                loop {
+                    let request = queue.pop().await;
+                    handle(request, &worker, launcher.as_ref(), grace, history.as_ref()).await;
                 }
             }
-        }
-    });
+            .instrument(info_span!("worker", id)),
+        );
+    }
 
     loop {
-        let (socket, _) = listener.accept().await.expect("Failed to accept");
-        let request_sender = request_sender.clone();
+        let socket = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                warn!(error = %e, "Failed to accept connection");
+                continue;
+            }
+        };
+        let queue = queue.clone();
+        let settings = settings.clone();
+        let history = history.clone();
         tokio::spawn(async move {
-            process(socket, request_sender).await;
+            connection::process(socket, queue, settings, history).await;
         });
     }
 }
 
-async fn handle(request: RequestHandle) {
+async fn handle<L: Launcher>(
+    request: RequestHandle,
+    worker: &Worker,
+    launcher: &L,
+    grace: Duration,
+    history: Option<&Arc<History>>,
+) {
     debug!("req: {:?}", request);
-    let RequestHandle { callback, request } = request;
-    let InternalRequest { config, request } = request;
-    let built = build::build(&request);
-    let mut child = match arma::start(&config, &built).await {
-        Ok((_profile, child)) => child,
-        Err(e) => {
-            error!("Failed to start server: {}", e);
-            let _ = callback.send(Response::Error(e));
-            return;
-        }
-    };
-    let _ = child.wait().await;
-    if built.path.join("timeout.txt").exists() {
-        let content = std::fs::read_to_string(built.path.join("timeout.txt"))
-            .expect("Failed to read timeout.txt");
-        let _ = callback.send(Response::Error(format!("timeout: {content}")));
+    let RequestHandle {
+        callback,
+        progress,
+        mut cancel,
+        request,
+    } = request;
+    if !matches!(cancel.try_recv(), Err(oneshot::error::TryRecvError::Empty)) {
+        debug!("Cancelled before starting");
+        let _ = callback.send(Response::Cancelled);
         return;
     }
-    match request {
-        Request::Execute(_) => {
-            match std::panic::catch_unwind(move || {
-                let content = std::fs::read_to_string(built.path.join("execute.txt"))
-                    .expect("Failed to read execute.txt");
-                serde_json::from_str(&content).expect("Failed to parse execute.txt")
-            }) {
-                Ok(result) => {
-                    let _ = callback.send(Response::Execute(Ok(result)));
-                }
-                Err(e) => {
-                    error!("Failed to read execute.txt: {:?}", e);
-                    let _ = callback.send(Response::Error(format!("panic: {e:?}")));
-                }
+    let boots = request.options.boots.unwrap_or(1);
+    let mut response: Option<Response> = None;
+    let mut version = None;
+    for boot in 1..=boots {
+        debug!(boot, boots, "Starting Arma");
+        let next = match run(&request, worker, launcher, grace, &progress, &mut cancel).await {
+            Ok((next, logged)) => {
+                version = logged.or(version);
+                next
+            }
+            Err(stopped) => {
+                let _ = callback.send(stopped);
+                return;
             }
+        };
+        let succeeded = matches!(
+            next,
+            Response::Execute(Ok(_)) | Response::Compare(Ok(_)) | Response::Matrix(Ok(_))
+        );
+        response = Some(match response {
+            Some(previous) => merge(previous, next),
+            None => next,
+        });
+        if !succeeded {
+            break;
         }
-        Request::Compare(_) => {
-            match std::panic::catch_unwind(move || {
-                let content = std::fs::read_to_string(built.path.join("compare.txt"))
-                    .expect("Failed to read compare.txt");
-                serde_json::from_str(&content).expect("Failed to parse compare.txt")
-            }) {
-                Ok(results) => {
-                    let _ = callback.send(Response::Compare(Ok(results)));
-                }
-                Err(e) => {
-                    error!("Failed to read compare.txt: {:?}", e);
-                    let _ = callback.send(Response::Error(format!("panic: {e:?}")));
+    }
+    let _ = progress.send(Progress::Finished);
+    let response = response.map_or_else(
+        || Response::Error("the request did not start Arma".to_string()),
+        |response| {
+            let response = timed(response, request.options.timing.unwrap_or_default());
+            assert_equal(response, request.options.assert_equal.as_ref())
+        },
+    );
+    if let Some(history) = history {
+        record(history, &request, version, &response).await;
+    }
+    let _ = callback.send(response);
+}
+
+/// Keep the results in the history, before they are sent so a query right after sees them.
+async fn record(
+    history: &Arc<History>,
+    request: &InternalRequest,
+    version: Option<String>,
+    response: &Response,
+) {
+    let history = history.clone();
+    let config = request.config.clone();
+    let options = request.options.clone();
+    let request = request.request.clone();
+    let response = response.clone();
+    let recorded = tokio::task::spawn_blocking(move || {
+        history.record(&config, version.as_deref(), &request, &options, &response)
+    })
+    .await;
+    match recorded {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!(error = %e, "Failed to record history"),
+        Err(e) => error!(error = %e, "History task failed"),
+    }
+}
+
+/// Start Arma once and read its result with the Arma build it logged,
+/// or the response to send straight away if it was stopped before it exited.
+async fn run<L: Launcher>(
+    request: &InternalRequest,
+    worker: &Worker,
+    launcher: &L,
+    grace: Duration,
+    progress: &ProgressSender,
+    cancel: &mut oneshot::Receiver<()>,
+) -> Result<(Response, Option<String>), Response> {
+    let InternalRequest {
+        config,
+        request,
+        timeout,
+        options,
+    } = request;
+    let timeout = *timeout;
+    let built = build::build(request, timeout, options);
+    let since = SystemTime::now();
+    // Installing a branch can take minutes, a cancel stops it too.
+    let started = tokio::select! {
+        started = launcher.launch(worker, config, &built, progress) => started,
+        _ = &mut *cancel => {
+            debug!("Cancelled while starting");
+            return Err(Response::Cancelled);
+        }
+    };
+    // The install is kept until the server exits, so it is not updated while in use.
+    let Launched {
+        mut child,
+        install: _install,
+    } = started.map_err(|e| {
+        error!("Failed to start server: {}", e);
+        Response::Error(e)
+    })?;
+    let _ = progress.send(Progress::Running);
+    // Always read, script errors are reported even when the lines are skipped.
+    let logs = || {
+        Logs::read(
+            &built.output(),
+            &worker.profiles(),
+            since,
+            !options.skip_logs,
+        )
+    };
+    // The in-game timeout relies on Arma getting far enough to load the extension.
+    let watchdog = tokio::time::sleep(Duration::from_secs(timeout) + grace);
+    let status = tokio::select! {
+        status = child.wait() => status,
+        () = watchdog => {
+            warn!(timeout, "Watchdog fired, killing server");
+            arma::kill(&mut child).await;
+            let logs = logs().run();
+            return Err(if built.path.join("started.txt").exists() {
+                Response::TimedOut { seconds: timeout, logs }
+            } else {
+                Response::NeverStarted { logs }
+            });
+        }
+        _ = cancel => {
+            debug!("Cancelled while running");
+            arma::kill(&mut child).await;
+            return Err(Response::Cancelled);
+        }
+    };
+    Ok(match status {
+        Ok(status) => {
+            let logs = logs();
+            (result(request, &built, status, &logs), logs.version())
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to wait for server");
+            (
+                Response::Error(format!("failed to wait for server: {e}")),
+                None,
+            )
+        }
+    })
+}
+
+/// Add the samples of another boot to the results so far.
+fn merge(previous: Response, next: Response) -> Response {
+    match (previous, next) {
+        (Response::Execute(Ok(mut previous)), Response::Execute(Ok(next))) => {
+            previous.samples.extend(next.samples);
+            previous.logs.extend(next.logs);
+            previous.errors.extend(next.errors);
+            Response::Execute(Ok(previous))
+        }
+        (Response::Compare(Ok(mut previous)), Response::Compare(Ok(next))) => {
+            for next in next {
+                if let Some(previous) = previous.iter_mut().find(|p| p.id == next.id) {
+                    previous.samples.extend(next.samples);
+                    previous.logs.extend(next.logs);
+                    previous.errors.extend(next.errors);
                 }
             }
+            Response::Compare(Ok(previous))
+        }
+        (Response::Matrix(Ok(mut previous)), Response::Matrix(Ok(next))) => {
+            for (previous, next) in previous.iter_mut().zip(next) {
+                previous.result.samples.extend(next.result.samples);
+                previous.result.logs.extend(next.result.logs);
+                previous.result.errors.extend(next.result.errors);
+            }
+            Response::Matrix(Ok(previous))
         }
+        // A boot that failed fails the whole request.
+        (_, next) => next,
     }
 }
 
-async fn process(mut socket: TcpStream, queue: tokio::sync::mpsc::Sender<RequestHandle>) {
-    let addr = socket.peer_addr().expect("Failed to get peer address");
-    trace!("[{}] Connection received", addr);
-    let (read, write) = socket.split();
-    let mut read = BufReader::new(read);
-    let mut write = BufWriter::new(write);
-    // Write the header ID to the client.
-    write
-        .write_all(HEADER_ID)
-        .await
-        .expect("Failed to write header ID");
-    write.flush().await.expect("Failed to flush");
-    // Expect the client to echo the header ID back to us.
-    let mut buf = [0; 16];
-    read.read_exact(&mut buf)
-        .await
-        .expect("Failed to read header ID");
-    if buf != *HEADER_ID {
-        error!("[{}] Invalid header ID", addr);
-        return;
+/// Record how the results were timed.
+fn timed(response: Response, timing: Timing) -> Response {
+    match response {
+        Response::Execute(Ok(mut result)) => {
+            result.timing = timing;
+            Response::Execute(Ok(result))
+        }
+        Response::Compare(Ok(mut results)) => {
+            for result in &mut results {
+                result.timing = timing;
+            }
+            Response::Compare(Ok(results))
+        }
+        Response::Matrix(Ok(mut results)) => {
+            for result in &mut results {
+                result.result.timing = timing;
+            }
+            Response::Matrix(Ok(results))
+        }
+        response => response,
     }
-    // The client has successfully connected.
-    info!("[{}] Connected", addr);
-
-    let server_config = ServerConfig::from_async_reader(&mut read)
-        .await
-        .expect("Failed to read server config");
-    debug!("[{}] Received server config: {:?}", addr, server_config);
+}
 
-    // Send wait packet to client
-    write.write_all(&[1]).await.expect("Failed to write ACK");
-    write.flush().await.expect("Failed to flush");
+/// Record where each result differs from the baseline's, if the client asked.
+fn assert_equal(response: Response, assert: Option<&AssertEqual>) -> Response {
+    let Some(assert) = assert else {
+        return response;
+    };
+    let mut results = match response {
+        Response::Compare(Ok(results)) => results,
+        response => return response,
+    };
+    let Some(expected) = results
+        .iter()
+        .find(|result| result.id == assert.baseline)
+        .map(|result| result.ret.clone())
+    else {
+        return Response::Compare(Ok(results));
+    };
+    for result in &mut results {
+        result.mismatches = mismatches(&expected, &result.ret, assert.tolerance);
+        if !result.mismatches.is_empty() {
+            debug!(
+                id = result.id,
+                mismatches = result.mismatches.len(),
+                "Result differs"
+            );
+        }
+    }
+    Response::Compare(Ok(results))
+}
 
-    loop {
-        let request = arma_bench::Request::from_async_reader(&mut read)
-            .await
-            .expect("Failed to read request");
-        debug!("[{}] Received request: {:?}", addr, request);
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        queue
-            .send(RequestHandle {
-                callback: tx,
-                request: InternalRequest {
-                    config: server_config.clone(),
-                    request,
-                },
-            })
-            .await
-            .expect("Failed to send request");
-        let response = rx.await.expect("Failed to receive response");
-        debug!("[{}] Sending response: {:?}", addr, response);
-        response
-            .write_async(&mut write)
-            .await
-            .expect("Failed to write response");
+/// Read the result of a finished run, or work out why there isn't one.
+fn result(request: &Request, built: &BuiltRequest, status: ExitStatus, logs: &Logs) -> Response {
+    if let Ok(content) = std::fs::read_to_string(built.path.join("timeout.txt")) {
+        return Response::TimedOut {
+            seconds: content.trim().parse().unwrap_or_default(),
+            logs: logs.run(),
+        };
+    }
+    if let Ok(file) = std::fs::read_to_string(built.path.join("compile_error.txt")) {
+        warn!(file, "Script failed to compile");
+        return Response::CompileFailed {
+            file,
+            errors: logs.run_errors(),
+            logs: logs.run(),
+        };
+    }
+    let file = match request {
+        Request::Execute(_) => "execute.txt",
+        Request::Compare(_) | Request::Matrix(_) => "compare.txt",
+    };
+    match std::fs::read_to_string(built.path.join(file)) {
+        Ok(content) => match request {
+            Request::Execute(_) => {
+                serde_json::from_str(&content).map(|mut result: ExecuteResult| {
+                    result.logs = logs.run();
+                    result.errors = logs.run_errors();
+                    Response::Execute(Ok(result))
+                })
+            }
+            Request::Compare(_) => {
+                serde_json::from_str(&content).map(|mut results: Vec<CompareResult>| {
+                    for result in &mut results {
+                        result.logs = logs.compare(result.id);
+                        result.errors = logs.compare_errors(result.id);
+                    }
+                    Response::Compare(Ok(results))
+                })
+            }
+            Request::Matrix(matrix) => {
+                serde_json::from_str(&content).map(|results: Vec<CompareResult>| {
+                    let results = matrix
+                        .parameters
+                        .iter()
+                        .zip(results)
+                        .map(|(parameters, result)| MatrixResult {
+                            parameters: parameters.clone(),
+                            result: ExecuteResult {
+                                time: result.time,
+                                iter: result.iter,
+                                ret: result.ret,
+                                logs: logs.compare(result.id),
+                                errors: logs.compare_errors(result.id),
+                                samples: result.samples,
+                                timing: result.timing,
+                            },
+                        })
+                        .collect();
+                    Response::Matrix(Ok(results))
+                })
+            }
+        }
+        .unwrap_or_else(|e| {
+            error!(error = %e, "Failed to parse {}", file);
+            Response::Error(format!("invalid {file}: {e}"))
+        }),
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            error!(error = %e, "Failed to read {}", file);
+            Response::Error(format!("failed to read {file}: {e}"))
+        }
+        Err(_) if !status.success() => {
+            warn!(%status, "Server crashed");
+            Response::Crashed {
+                exit_code: status.code(),
+                signal: status.signal(),
+                logs: logs.run(),
+            }
+        }
+        Err(_) if !built.path.join("started.txt").exists() => {
+            warn!("Server exited before the benchmark started");
+            Response::NeverStarted { logs: logs.run() }
+        }
+        Err(_) => {
+            warn!("Server exited without a result");
+            Response::ResultMissing { logs: logs.run() }
+        }
     }
 }
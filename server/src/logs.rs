@@ -0,0 +1,166 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use arma_bench::ScriptError;
+
+/// The most lines returned for a run, older lines are dropped first.
+const MAX_LINES: usize = 1000;
+
+/// What Arma logged during a run, split on the markers the bootstrap logs.
+#[derive(Debug, Default)]
+pub struct Logs {
+    lines: Vec<String>,
+    /// Whether the client wants the lines, errors are reported either way.
+    keep: bool,
+}
+
+impl Logs {
+    /// Read the RPT Arma wrote to `profiles` since `since`,
+    /// falling back to the process `output` if there isn't one.
+    pub fn read(output: &Path, profiles: &Path, since: SystemTime, keep: bool) -> Self {
+        let path = rpt(profiles, since).unwrap_or_else(|| output.to_path_buf());
+        let content = std::fs::read(path).unwrap_or_default();
+        Self {
+            lines: String::from_utf8_lossy(&content)
+                .lines()
+                .map(ToString::to_string)
+                .collect(),
+            keep,
+        }
+    }
+
+    /// The lines logged while the benchmark ran,
+    /// or the last lines logged if it never got that far.
+    pub fn run(&self) -> Vec<String> {
+        self.tail(self.run_lines())
+    }
+
+    /// The lines logged while benchmarking the compare script `id`.
+    pub fn compare(&self, id: u16) -> Vec<String> {
+        self.tail(self.compare_lines(id))
+    }
+
+    /// The script errors logged while the benchmark ran.
+    pub fn run_errors(&self) -> Vec<ScriptError> {
+        errors(self.run_lines())
+    }
+
+    /// The script errors logged while benchmarking the compare script `id`.
+    pub fn compare_errors(&self, id: u16) -> Vec<ScriptError> {
+        errors(self.compare_lines(id))
+    }
+
+    /// The Arma build, from the `Version: 2.18.152405` line at the top of the RPT.
+    pub fn version(&self) -> Option<String> {
+        self.lines.iter().find_map(|line| {
+            without_time(line)
+                .strip_prefix("Version:")
+                .map(|version| version.trim().to_string())
+        })
+    }
+
+    fn run_lines(&self) -> &[String] {
+        let Some(begin) = self.find("tab: begin", 0) else {
+            return &self.lines;
+        };
+        let end = self.find("tab: end", begin).unwrap_or(self.lines.len());
+        &self.lines[begin + 1..end]
+    }
+
+    fn compare_lines(&self, id: u16) -> &[String] {
+        let Some(start) = self.find(&format!("tab: benchmarking {id}"), 0) else {
+            return &[];
+        };
+        let end = self.lines[start + 1..]
+            .iter()
+            .position(|line| line.contains("\"tab: "))
+            .map_or(self.lines.len(), |i| start + 1 + i);
+        &self.lines[start + 1..end]
+    }
+
+    /// The index of the first line from `from` with `marker`, as `diag_log` quotes it.
+    fn find(&self, marker: &str, from: usize) -> Option<usize> {
+        let marker = format!("\"{marker}\"");
+        self.lines[from..]
+            .iter()
+            .position(|line| line.contains(&marker))
+            .map(|i| from + i)
+    }
+
+    fn tail(&self, lines: &[String]) -> Vec<String> {
+        if !self.keep {
+            return Vec::new();
+        }
+        lines[lines.len().saturating_sub(MAX_LINES)..].to_vec()
+    }
+}
+
+/// Parse the `Error in expression` blocks Arma logs, which look like:
+///
+/// ```text
+///  9:41:03 Error in expression <_a + "x">
+///  9:41:03   Error position: <+ "x">
+///  9:41:03   Error +: Type String, expected Number
+///  9:41:03 File \tab\bench.sqf..., line 2
+/// ```
+fn errors(lines: &[String]) -> Vec<ScriptError> {
+    let mut errors = Vec::new();
+    let mut current: Option<ScriptError> = None;
+    for line in lines {
+        let text = without_time(line);
+        if text.starts_with("Error in expression <") {
+            errors.extend(current.replace(ScriptError {
+                file: String::new(),
+                line: None,
+                message: String::new(),
+            }));
+            continue;
+        }
+        let Some(error) = current.as_mut() else {
+            continue;
+        };
+        if let Some(message) = text
+            .strip_prefix("Error ")
+            .filter(|message| !message.starts_with("position:"))
+        {
+            error.message = message.trim().to_string();
+        } else if let Some(location) = text.strip_prefix("File ") {
+            let (file, line) = location.rsplit_once(", line ").unwrap_or((location, ""));
+            // Functions add their name, `\tab\bench.sqf [TAB_fnc_bench]...`
+            let file = file.split(" [").next().unwrap_or(file);
+            let file = file.trim_end_matches("...");
+            error.file = file.strip_prefix("\\tab\\").unwrap_or(file).to_string();
+            error.line = line.trim().parse().ok();
+            errors.extend(current.take());
+        }
+    }
+    errors.extend(current);
+    errors
+}
+
+/// A log line without the time Arma puts before it.
+fn without_time(line: &str) -> &str {
+    let line = line.trim_start();
+    match line.split_once(' ') {
+        Some((time, rest))
+            if time.contains(':') && time.chars().all(|c| c.is_ascii_digit() || c == ':') =>
+        {
+            rest.trim_start()
+        }
+        _ => line,
+    }
+}
+
+/// The newest RPT in `profiles` modified since `since`.
+fn rpt(profiles: &Path, since: SystemTime) -> Option<PathBuf> {
+    std::fs::read_dir(profiles)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "rpt"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, _)| *modified >= since)
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
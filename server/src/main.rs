@@ -1,3 +1,4 @@
+use arma_bench_server::{Arma, Settings, SteamCmd};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -16,7 +17,8 @@ async fn main() {
 
     let addr = std::env::var("TAB_ADDR")
         .unwrap_or_else(|_| format!("0.0.0.0:{}", arma_bench::DEFAULT_PORT));
-    arma_bench_server::server(addr).await;
+    let settings = Settings::from_env().unwrap_or_else(|e| panic!("{e}"));
+    arma_bench_server::server(addr, settings, Arma::new(SteamCmd::default())).await;
 }
 
 fn require_env(name: &str) -> String {
@@ -0,0 +1,74 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+use arma_bench::Progress;
+use tokio::sync::Notify;
+
+use crate::RequestHandle;
+
+/// Requests waiting for a worker, in the order they were received.
+///
+/// Every request is told its position when it is added, and again whenever it moves up.
+#[derive(Default)]
+pub struct Queue {
+    waiting: Mutex<VecDeque<(u64, RequestHandle)>>,
+    notify: Notify,
+    next_ticket: AtomicU64,
+}
+
+impl Queue {
+    /// Add a request to the back of the queue, returning a ticket that can remove it again.
+    pub fn push(&self, request: RequestHandle) -> u64 {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+            request.report(Progress::Queued {
+                position: waiting.len(),
+            });
+            waiting.push_back((ticket, request));
+        }
+        self.notify.notify_one();
+        ticket
+    }
+
+    /// Remove a request that has not been picked up by a worker yet.
+    pub fn remove(&self, ticket: u64) -> Option<RequestHandle> {
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        let index = waiting.iter().position(|(t, _)| *t == ticket)?;
+        let (_, request) = waiting.remove(index)?;
+        for (position, (_, request)) in waiting.iter().enumerate().skip(index) {
+            request.report(Progress::Queued { position });
+        }
+        drop(waiting);
+        Some(request)
+    }
+
+    /// Wait for the next request.
+    pub async fn pop(&self) -> RequestHandle {
+        loop {
+            if let Some(request) = self.try_pop() {
+                return request;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    fn try_pop(&self) -> Option<RequestHandle> {
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        let (_, request) = waiting.pop_front()?;
+        for (position, (_, request)) in waiting.iter().enumerate() {
+            request.report(Progress::Queued { position });
+        }
+        if !waiting.is_empty() {
+            // Only one permit is stored, make sure another worker picks up the rest.
+            self.notify.notify_one();
+        }
+        drop(waiting);
+        Some(request)
+    }
+}
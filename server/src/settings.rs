@@ -0,0 +1,97 @@
+use std::{ops::RangeInclusive, path::PathBuf, time::Duration};
+
+use arma_bench::{DEFAULT_MAX_MESSAGE_SIZE, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The largest message accepted from a client, in bytes.
+    pub max_message_size: u64,
+    /// The protocol versions clients may negotiate.
+    pub protocol_versions: RangeInclusive<u16>,
+    /// How many requests can run at the same time, each in its own Arma instance.
+    pub workers: usize,
+    /// Pin each worker to its own set of this many CPUs, so parallel runs do not compete.
+    pub cpus_per_worker: Option<usize>,
+    /// The longest timeout a request may ask for, in seconds.
+    pub max_timeout: u64,
+    /// How long past its timeout a request may run before Arma is killed,
+    /// covering the time it takes Arma to start.
+    pub watchdog_grace: Duration,
+    /// The most times a request may ask to start Arma.
+    pub max_boots: u32,
+    /// The database every result is kept in, `None` keeps nothing.
+    /// `:memory:` keeps them until the server stops.
+    pub history: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            protocol_versions: MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION,
+            workers: 1,
+            cpus_per_worker: None,
+            max_timeout: 600,
+            watchdog_grace: Duration::from_mins(1),
+            max_boots: 10,
+            history: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings from `TAB_*` environment variables,
+    /// using the defaults for any that are not set.
+    ///
+    /// # Errors
+    /// Returns a string error if a variable is set to an invalid value.
+    pub fn from_env() -> Result<Self, String> {
+        let mut settings = Self::default();
+        if let Some(max) = env("TAB_MAX_MESSAGE_SIZE")? {
+            settings.max_message_size = max;
+        }
+        if let Some(workers) = env("TAB_WORKERS")? {
+            if workers == 0 {
+                return Err("TAB_WORKERS must be at least 1".to_string());
+            }
+            settings.workers = workers;
+        }
+        if let Some(cpus) = env::<usize>("TAB_CPUS_PER_WORKER")? {
+            if cpus == 0 {
+                return Err("TAB_CPUS_PER_WORKER must be at least 1".to_string());
+            }
+            let available = std::thread::available_parallelism().map_or(1, usize::from);
+            if cpus.saturating_mul(settings.workers) > available {
+                return Err(format!(
+                    "TAB_CPUS_PER_WORKER of {cpus} for {} workers needs more than the {available} CPUs available",
+                    settings.workers
+                ));
+            }
+            settings.cpus_per_worker = Some(cpus);
+        }
+        if let Some(max) = env("TAB_MAX_TIMEOUT")? {
+            settings.max_timeout = max;
+        }
+        if let Some(grace) = env("TAB_WATCHDOG_GRACE")? {
+            settings.watchdog_grace = Duration::from_secs(grace);
+        }
+        if let Some(max) = env("TAB_MAX_BOOTS")? {
+            settings.max_boots = max;
+        }
+        settings.history =
+            env::<PathBuf>("TAB_HISTORY")?.filter(|path| !path.as_os_str().is_empty());
+        Ok(settings)
+    }
+}
+
+fn env<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    std::env::var(name).map_or(Ok(None), |value| {
+        value
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("{name} is invalid: {e}"))
+    })
+}
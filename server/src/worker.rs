@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use crate::Settings;
+
+/// Runs one request at a time, several workers can run in parallel.
+#[derive(Debug, Clone)]
+pub struct Worker {
+    pub id: usize,
+    /// The CPUs processes started by this worker should be pinned to.
+    pub cpus: Option<Vec<usize>>,
+    /// A scratch directory only used by this worker.
+    pub dir: PathBuf,
+}
+
+impl Worker {
+    #[must_use]
+    pub fn new(id: usize, settings: &Settings) -> Self {
+        Self {
+            id,
+            cpus: settings
+                .cpus_per_worker
+                .map(|count| (id * count..(id + 1) * count).collect()),
+            dir: std::env::temp_dir()
+                .join("arma_bench_workers")
+                .join(id.to_string()),
+        }
+    }
+
+    /// The directory Arma should use for its profile and logs.
+    #[must_use]
+    pub fn profiles(&self) -> PathBuf {
+        self.dir.join("profiles")
+    }
+}
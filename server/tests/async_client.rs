@@ -0,0 +1,54 @@
+use std::sync::Once;
+
+use arma_bench::{AsyncClient, CompareRequest, Error, ServerConfig};
+use arma_bench_server::{Arma, Settings, SteamCmd};
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31847;
+
+fn start_server() {
+    common::start_server(
+        &SERVER,
+        PORT,
+        Settings::default(),
+        Arma::new(SteamCmd::default()),
+    );
+}
+
+#[tokio::test]
+async fn client() {
+    start_server();
+    AsyncClient::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .await
+        .expect("Failed to connect");
+}
+
+#[tokio::test]
+async fn execute_without_arma() {
+    start_server();
+    let client = AsyncClient::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .await
+        .expect("Failed to connect");
+    // No server is installed in the test environment, so the request should
+    // come back as an error instead of hanging or dropping the connection.
+    let res = client.execute("1 + 2").await;
+    assert!(matches!(res, Err(Error::ServerError(_))));
+}
+
+#[tokio::test]
+async fn compare_without_arma() {
+    start_server();
+    let client = AsyncClient::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .await
+        .expect("Failed to connect");
+    let res = client
+        .compare(vec![CompareRequest {
+            id: 0,
+            sqfc: false,
+            content: b"1 + 2".to_vec(),
+        }])
+        .await;
+    assert!(matches!(res, Err(Error::ServerError(_))));
+}
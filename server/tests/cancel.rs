@@ -0,0 +1,97 @@
+use std::{
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+use arma_bench::{Client, Error, Progress, Request, ServerConfig};
+use arma_bench_server::{BuiltRequest, Launched, Launcher, ProgressSender, Settings, Worker};
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31852;
+static INSTALLING_SERVER: Once = Once::new();
+static INSTALLING_PORT: u16 = 31870;
+
+/// Takes as long as installing a branch before starting [`FakeArma`].
+struct SlowInstall;
+
+impl Launcher for SlowInstall {
+    async fn launch(
+        &self,
+        worker: &Worker,
+        config: &ServerConfig,
+        built: &BuiltRequest,
+        progress: &ProgressSender,
+    ) -> Result<Launched, String> {
+        let _ = progress.send(Progress::Installing);
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        FakeArma.launch(worker, config, built, progress).await
+    }
+}
+
+fn start_server() {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+}
+
+fn client() -> Client {
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+#[test]
+fn cancel() {
+    start_server();
+    let running = client();
+    let queued = client();
+
+    let start = Instant::now();
+    let running_request = running
+        .submit(Request::Execute("// fake:sleep=5000".to_string()))
+        .expect("Failed to submit");
+    std::thread::sleep(Duration::from_millis(300));
+    let queued_request = queued
+        .submit(Request::Execute("1".to_string()))
+        .expect("Failed to submit");
+    std::thread::sleep(Duration::from_millis(300));
+
+    // The second request is still waiting for the only worker.
+    assert!(queued_request.cancel().expect("Failed to cancel"));
+    let res = queued_request.wait().expect("Failed to wait");
+    assert!(matches!(res.into_execute(), Err(Error::Cancelled)));
+
+    // The first is running, and gets killed.
+    assert!(running
+        .cancel(running_request.id())
+        .expect("Failed to cancel"));
+    let res = running_request.wait().expect("Failed to wait");
+    assert!(matches!(res.into_execute(), Err(Error::Cancelled)));
+    assert!(start.elapsed() < Duration::from_secs(3));
+
+    // Finished requests can't be cancelled again, and both clients can still be used.
+    assert!(!running.cancel(0).expect("Failed to cancel"));
+    running.execute("2").expect("Failed to execute");
+    queued.execute("3").expect("Failed to execute");
+}
+
+#[test]
+fn cancel_while_installing() {
+    common::start_server(
+        &INSTALLING_SERVER,
+        INSTALLING_PORT,
+        Settings::default(),
+        SlowInstall,
+    );
+    let client = Client::connect_with_port("localhost", INSTALLING_PORT, &ServerConfig::default())
+        .expect("Failed to connect");
+    let start = Instant::now();
+    let request = client
+        .submit(Request::Execute("1".to_string()))
+        .expect("Failed to submit");
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(request.cancel().expect("Failed to cancel"));
+    let res = request.wait().expect("Failed to wait");
+    assert!(matches!(res.into_execute(), Err(Error::Cancelled)));
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
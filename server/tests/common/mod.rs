@@ -0,0 +1,47 @@
+// Each test file uses a different part of this module.
+#![allow(dead_code)]
+
+use std::sync::Once;
+
+use arma_bench::{Progress, ServerConfig};
+use arma_bench_server::{BuiltRequest, Launched, Launcher, ProgressSender, Settings, Worker};
+use tokio::process::Command;
+
+/// Start a server on `port` the first time this is called with `once`,
+/// so every test in a file shares it.
+pub fn start_server(once: &'static Once, port: u16, settings: Settings, launcher: impl Launcher) {
+    once.call_once(move || {
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .expect("Failed to create runtime")
+                .block_on(async {
+                    arma_bench_server::server(format!("localhost:{port}"), settings, launcher)
+                        .await;
+                });
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+}
+
+/// Runs requests with the `fake-arma` binary instead of Arma.
+pub struct FakeArma;
+
+impl Launcher for FakeArma {
+    async fn launch(
+        &self,
+        _worker: &Worker,
+        _config: &ServerConfig,
+        built: &BuiltRequest,
+        progress: &ProgressSender,
+    ) -> Result<Launched, String> {
+        let _ = progress.send(Progress::Starting);
+        let output = std::fs::File::create(built.output()).map_err(|e| e.to_string())?;
+        Command::new(env!("CARGO_BIN_EXE_fake-arma"))
+            .process_group(0)
+            .stdout(output)
+            .arg(&built.path)
+            .spawn()
+            .map(Launched::from)
+            .map_err(|e| e.to_string())
+    }
+}
@@ -3,29 +3,87 @@ use std::{
     sync::Once,
 };
 
-use arma_bench::{Client, ServerConfig};
+use arma_bench::{
+    Client, ClientHello, Error, Message, Request, Response, ServerConfig, ServerHello, HEADER_ID,
+    LEGACY_HEADER_ID, PROTOCOL_VERSION,
+};
+use arma_bench_server::{Arma, Settings, SteamCmd};
+
+mod common;
 
 static SERVER: Once = Once::new();
 static PORT: u16 = 31846;
+static LEGACY_PORT: u16 = 31873;
 
 fn start_server() {
-    SERVER.call_once(|| {
-        std::thread::spawn(|| {
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create runtime")
-                .block_on(async {
-                    arma_bench_server::server(format!("localhost:{PORT}")).await;
-                });
-        });
-    });
-    std::thread::sleep(std::time::Duration::from_millis(100));
+    common::start_server(
+        &SERVER,
+        PORT,
+        Settings::default(),
+        Arma::new(SteamCmd::default()),
+    );
 }
 
 #[test]
 fn client() {
     start_server();
-    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+    let client = Client::connect_with_port("localhost", PORT, &ServerConfig::default())
         .expect("Failed to connect");
+    assert_eq!(client.protocol().version, PROTOCOL_VERSION);
+}
+
+/// Send a hello with the given versions, returning the server's reply.
+fn hello(min_version: u16, max_version: u16) -> (std::net::TcpStream, ServerHello) {
+    let mut stream =
+        std::net::TcpStream::connect(format!("localhost:{PORT}")).expect("Failed to connect");
+    stream
+        .write_all(HEADER_ID)
+        .expect("Failed to send header ID");
+    let mut buf = [0; 16];
+    stream
+        .read_exact(&mut buf)
+        .expect("Failed to read header ID");
+    ClientHello {
+        min_version,
+        max_version,
+        capabilities: vec!["from-the-future".to_string()],
+    }
+    .write(&mut stream)
+    .expect("Failed to send hello");
+    let reply = ServerHello::from_reader(&mut stream).expect("Failed to read hello");
+    (stream, reply)
+}
+
+#[test]
+fn newer_client() {
+    start_server();
+    let (_, reply) = hello(PROTOCOL_VERSION, PROTOCOL_VERSION + 10);
+    let ServerHello::Accepted(protocol) = reply else {
+        panic!("Expected the server to accept, got {reply:?}");
+    };
+    assert_eq!(protocol.version, PROTOCOL_VERSION);
+    // Capabilities the server does not know about are dropped.
+    assert!(protocol.capabilities.is_empty());
+}
+
+#[test]
+fn unsupported_version() {
+    start_server();
+    let (mut stream, reply) = hello(0, 1);
+    assert!(matches!(
+        reply,
+        ServerHello::UnsupportedVersion { max_version, .. } if max_version == PROTOCOL_VERSION
+    ));
+    // server should have disconnected
+    let mut buf = [0; 1];
+    assert_eq!(stream.read(&mut buf).expect("Failed to read"), 0);
+}
+
+#[test]
+fn refused() {
+    // Nothing listens on this port, the client should report it instead of panicking.
+    let res = Client::connect_with_port("localhost", PORT + 100, &ServerConfig::default());
+    assert!(matches!(res, Err(Error::Io(_))));
 }
 
 #[test]
@@ -40,7 +98,7 @@ fn bad_header() {
     stream
         .read_exact(&mut buf)
         .expect("Failed to read header ID");
-    assert_eq!(&buf, arma_bench::HEADER_ID);
+    assert_eq!(&buf, HEADER_ID);
     // server should have disconnected
     let mut buf = [0; 1];
     stream
@@ -49,3 +107,60 @@ fn bad_header() {
     let res = stream.read_exact(&mut buf);
     assert!(res.is_err());
 }
+
+/// Write a message like clients from before the handshake did, without field names.
+fn write_legacy(stream: &mut std::net::TcpStream, message: &impl serde::Serialize) {
+    let payload = rmp_serde::to_vec(message).expect("Failed to encode");
+    stream
+        .write_all(&(payload.len() as u64).to_le_bytes())
+        .expect("Failed to send length");
+    stream.write_all(&payload).expect("Failed to send payload");
+}
+
+#[test]
+fn legacy_client() {
+    start_server();
+    let mut stream =
+        std::net::TcpStream::connect(format!("localhost:{PORT}")).expect("Failed to connect");
+    stream
+        .write_all(LEGACY_HEADER_ID)
+        .expect("Failed to send header ID");
+    let mut buf = [0; 16];
+    stream
+        .read_exact(&mut buf)
+        .expect("Failed to read header ID");
+    assert_eq!(&buf, LEGACY_HEADER_ID);
+    write_legacy(&mut stream, &ServerConfig::default());
+    let mut buf = [0; 1];
+    stream.read_exact(&mut buf).expect("Failed to read ACK");
+    assert_eq!(buf[0], 1);
+    // The first request is answered with why the client can not connect.
+    write_legacy(&mut stream, &Request::Execute("1".to_string()));
+    let response = Response::from_reader(&mut stream).expect("Failed to read response");
+    assert!(
+        matches!(&response, Response::Error(e) if e.contains("protocol version 1")),
+        "{response:?}"
+    );
+    let mut buf = [0; 1];
+    assert_eq!(stream.read(&mut buf).expect("Failed to read"), 0);
+}
+
+#[test]
+fn legacy_server() {
+    let listener =
+        std::net::TcpListener::bind(format!("localhost:{LEGACY_PORT}")).expect("Failed to bind");
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("Failed to accept");
+        let _ = stream.write_all(LEGACY_HEADER_ID);
+        let mut buf = [0; 16];
+        let _ = stream.read_exact(&mut buf);
+    });
+    let res = Client::connect_with_port("localhost", LEGACY_PORT, &ServerConfig::default());
+    assert!(matches!(
+        res,
+        Err(Error::UnsupportedVersion {
+            min_version: 1,
+            max_version: 1
+        })
+    ));
+}
@@ -0,0 +1,83 @@
+use std::{sync::Once, time::Duration};
+
+use arma_bench::{Client, Error, ExecuteResult, Options, Request, Response, ServerConfig};
+use arma_bench_server::Settings;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31855;
+
+fn start_server() {
+    let settings = Settings {
+        workers: 2,
+        watchdog_grace: Duration::from_millis(200),
+        ..Default::default()
+    };
+    common::start_server(&SERVER, PORT, settings, FakeArma);
+}
+
+fn execute(content: &str) -> Result<ExecuteResult, Error> {
+    start_server();
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+        .execute(content)
+}
+
+#[test]
+fn signal() {
+    let res = execute("// fake:crash");
+    assert!(matches!(
+        res,
+        Err(Error::Crashed {
+            exit_code: None,
+            signal: Some(libc::SIGABRT),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn exit_code() {
+    let res = execute("// fake:exit=3");
+    assert!(matches!(
+        res,
+        Err(Error::Crashed {
+            exit_code: Some(3),
+            signal: None,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn never_started() {
+    let res = execute("// fake:never-start");
+    assert!(matches!(res, Err(Error::NeverStarted { .. })));
+}
+
+#[test]
+fn hung_before_starting() {
+    start_server();
+    let client = Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect");
+    let res = client
+        .submit_with_options(
+            Request::Execute("// fake:never-start fake:hang".to_string()),
+            Options {
+                timeout: Some(1),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait");
+    assert!(matches!(res, Response::NeverStarted { .. }));
+}
+
+#[test]
+fn result_missing() {
+    let res = execute("// fake:no-result");
+    assert!(matches!(res, Err(Error::ResultMissing { .. })));
+}
@@ -0,0 +1,74 @@
+use std::sync::Once;
+
+use arma_bench::{AssertEqual, Client, CompareRequest, Options, Request, Response, ServerConfig};
+use arma_bench_server::Settings;
+use arma_rs::Value;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31859;
+
+fn start_server() {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+}
+
+fn submit(request: Request, baseline: u16) -> Response {
+    start_server();
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+        .submit_with_options(
+            request,
+            Options {
+                assert_equal: Some(AssertEqual {
+                    baseline,
+                    tolerance: 0.001,
+                }),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+}
+
+fn script(id: u16, content: &str) -> CompareRequest {
+    CompareRequest {
+        id,
+        sqfc: false,
+        content: content.as_bytes().to_vec(),
+    }
+}
+
+#[test]
+fn mismatches() {
+    let results = submit(
+        Request::Compare(vec![script(0, "1"), script(1, "1.0001"), script(2, "2")]),
+        0,
+    )
+    .into_compare()
+    .expect("Failed to compare");
+    assert!(results[0].mismatches.is_empty());
+    // Within the tolerance.
+    assert!(results[1].mismatches.is_empty());
+    assert_eq!(results[2].mismatches.len(), 1);
+    assert_eq!(results[2].mismatches[0].expected, Value::Number(1.0));
+    assert_eq!(results[2].mismatches[0].actual, Value::Number(2.0));
+}
+
+#[test]
+fn missing_baseline() {
+    let res = submit(Request::Compare(vec![script(0, "1")]), 5);
+    assert!(
+        matches!(res, Response::Error(e) if e == "baseline 5 is not one of the compared scripts")
+    );
+}
+
+#[test]
+fn execute() {
+    let res = submit(Request::Execute("1".to_string()), 0);
+    assert!(
+        matches!(res, Response::Error(e) if e.contains("only be asserted equal when comparing"))
+    );
+}
@@ -0,0 +1,111 @@
+use std::sync::Once;
+
+use arma_bench::{Client, CompareRequest, Error, Options, Request, ScriptError, ServerConfig};
+use arma_bench_server::Settings;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31857;
+
+fn start_server() {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+}
+
+fn client() -> Client {
+    start_server();
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+#[test]
+fn runtime_error() {
+    let result = client()
+        .execute("private _a = 1;\n// fake:error=Undefined variable in expression: _b")
+        .expect("Failed to execute");
+    assert_eq!(
+        result.errors,
+        [ScriptError {
+            file: "bench.sqf".to_string(),
+            line: Some(2),
+            message: "Undefined variable in expression: _b".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn no_errors() {
+    let result = client().execute("1").expect("Failed to execute");
+    assert!(result.errors.is_empty());
+}
+
+#[test]
+fn compare_errors() {
+    let results = client()
+        .compare(vec![
+            CompareRequest {
+                id: 0,
+                sqfc: false,
+                content: b"0".to_vec(),
+            },
+            CompareRequest {
+                id: 1,
+                sqfc: false,
+                content: b"// fake:error=Zero divisor".to_vec(),
+            },
+        ])
+        .expect("Failed to compare");
+    assert!(results[0].errors.is_empty());
+    assert_eq!(results[1].errors.len(), 1);
+    assert_eq!(results[1].errors[0].file, "1.sqf");
+    assert_eq!(results[1].errors[0].message, "Zero divisor");
+}
+
+#[test]
+fn compile_error() {
+    let res = client().execute("1\n// fake:compile-error");
+    let Err(Error::CompileFailed { file, errors, .. }) = res else {
+        panic!("Expected a compile failure, got {res:?}");
+    };
+    assert_eq!(file, "bench.sqf");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, Some(2));
+    assert_eq!(errors[0].message, "Missing ;");
+}
+
+#[test]
+fn compile_error_in_compare() {
+    let res = client().compare(vec![
+        CompareRequest {
+            id: 0,
+            sqfc: false,
+            content: b"0".to_vec(),
+        },
+        CompareRequest {
+            id: 3,
+            sqfc: false,
+            content: b"// fake:compile-error".to_vec(),
+        },
+    ]);
+    assert!(matches!(res, Err(Error::CompileFailed { file, .. }) if file == "3.sqf"));
+}
+
+#[test]
+fn skipped_logs_keep_errors() {
+    let result = client()
+        .submit_with_options(
+            Request::Execute("// fake:error=Generic error in expression".to_string()),
+            Options {
+                skip_logs: true,
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+        .into_execute()
+        .expect("Failed to execute");
+    assert!(result.logs.is_empty());
+    assert_eq!(result.errors.len(), 1);
+}
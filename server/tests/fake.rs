@@ -0,0 +1,79 @@
+use std::sync::Once;
+
+use arma_bench::{Client, CompareRequest, Error, Progress, ServerConfig};
+use arma_bench_server::Settings;
+use arma_rs::Value;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31849;
+
+fn start_server() {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+}
+
+fn client() -> Client {
+    start_server();
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+#[test]
+fn execute() {
+    let result = client().execute("3").expect("Failed to execute");
+    assert_eq!(result.iter, 10000);
+    assert_eq!(result.ret, Value::Number(3.0));
+}
+
+#[test]
+fn compare() {
+    let results = client()
+        .compare(vec![
+            CompareRequest {
+                id: 0,
+                sqfc: false,
+                content: b"1".to_vec(),
+            },
+            CompareRequest {
+                id: 1,
+                sqfc: false,
+                content: b"\"one\"".to_vec(),
+            },
+        ])
+        .expect("Failed to compare");
+    assert_eq!(results.len(), 2);
+    let first = results.iter().find(|r| r.id == 0).expect("Missing id 0");
+    assert_eq!(first.ret, Value::Number(1.0));
+    let second = results.iter().find(|r| r.id == 1).expect("Missing id 1");
+    assert_eq!(second.ret, Value::String("\"one\"".to_string()));
+}
+
+#[test]
+fn timeout() {
+    let res = client().execute("// fake:timeout");
+    assert!(matches!(res, Err(Error::RunTimedOut { seconds: 30, .. })));
+}
+
+#[test]
+fn sequential_requests() {
+    let client = client();
+    for i in 0..3 {
+        let result = client.execute(&i.to_string()).expect("Failed to execute");
+        assert_eq!(result.ret, Value::Number(f64::from(i)));
+    }
+}
+
+#[test]
+fn progress() {
+    let mut progress = Vec::new();
+    client()
+        .execute_with_progress("1", |p| progress.push(p))
+        .expect("Failed to execute");
+    assert!(matches!(progress.first(), Some(Progress::Queued { .. })));
+    assert_eq!(
+        progress[progress.len() - 3..],
+        [Progress::Starting, Progress::Running, Progress::Finished]
+    );
+}
@@ -0,0 +1,233 @@
+use std::{
+    path::PathBuf,
+    sync::Once,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use arma_bench::{
+    script_hash, Client, CompareRequest, Error, HistoryQuery, Options, Parameters, Request,
+    ServerConfig,
+};
+use arma_bench_server::Settings;
+use arma_rs::Value;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31867;
+static PLAIN_SERVER: Once = Once::new();
+static PLAIN_PORT: u16 = 31869;
+
+fn client() -> Client {
+    // Kept in memory, so nothing is left behind when the tests end.
+    common::start_server(
+        &SERVER,
+        PORT,
+        Settings {
+            history: Some(PathBuf::from(":memory:")),
+            ..Default::default()
+        },
+        FakeArma,
+    );
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+fn by_hash(content: &str) -> HistoryQuery {
+    HistoryQuery {
+        hash: Some(script_hash(content.as_bytes())),
+        ..Default::default()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+#[test]
+fn execute() {
+    let content = "// fake:log=execute\n1";
+    let client = client();
+    let result = client.execute(content).expect("Failed to execute");
+    let records = client.history(by_hash(content)).expect("Failed to query");
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record.hash, script_hash(content.as_bytes()));
+    assert_eq!(record.version.as_deref(), Some("2.18.152405"));
+    assert_eq!(record.config.branch, ServerConfig::default().branch);
+    assert!(record.config.branch_password.is_empty());
+    assert!(record.parameters.is_empty());
+    assert_eq!(record.iterations, result.iter);
+    assert_eq!(record.samples, result.samples);
+    assert_eq!(record.ret, result.ret);
+    assert_eq!(record.options, Options::default());
+    assert!(record.failure.is_none());
+    // Logs are only read when asked for.
+    assert!(record.logs.is_empty());
+    assert!(record.statistics().is_some());
+    let records = client
+        .history(HistoryQuery {
+            logs: true,
+            ..by_hash(content)
+        })
+        .expect("Failed to query");
+    assert_eq!(records[0].logs, ["execute"]);
+}
+
+#[test]
+fn options() {
+    let content = "// history: options\n1";
+    let client = client();
+    client
+        .submit_with_options(
+            Request::Execute(content.to_string()),
+            Options {
+                timeout: Some(60),
+                repetitions: Some(2),
+                setup: Some("x = 1".to_string()),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+        .into_execute()
+        .expect("Failed to execute");
+    let records = client.history(by_hash(content)).expect("Failed to query");
+    // Only what changes the measurement is kept.
+    assert_eq!(
+        records[0].options,
+        Options {
+            repetitions: Some(2),
+            setup: Some("x = 1".to_string()),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn compare() {
+    let files = ["// history: zero\n0", "// history: one\n1"];
+    let client = client();
+    client
+        .compare(
+            files
+                .iter()
+                .zip(0..)
+                .map(|(content, id)| CompareRequest {
+                    id,
+                    sqfc: false,
+                    content: content.as_bytes().to_vec(),
+                })
+                .collect(),
+        )
+        .expect("Failed to compare");
+    // Each script is kept on its own.
+    for content in files {
+        let records = client.history(by_hash(content)).expect("Failed to query");
+        assert_eq!(records.len(), 1);
+    }
+}
+
+#[test]
+fn matrix() {
+    let template = "// history: matrix\n_size";
+    let parameters: Vec<Parameters> = [1.0, 2.0]
+        .into_iter()
+        .map(|size| Parameters::from([("_size".to_string(), Value::Number(size))]))
+        .collect();
+    let client = client();
+    client
+        .matrix(template, parameters.clone())
+        .expect("Failed to run matrix");
+    let records = client.history(by_hash(template)).expect("Failed to query");
+    let recorded: Vec<_> = records
+        .into_iter()
+        .map(|record| record.parameters)
+        .collect();
+    assert_eq!(recorded, parameters);
+}
+
+#[test]
+fn failures_are_kept() {
+    let content = "// fake:no-result";
+    let client = client();
+    let error = client
+        .execute(content)
+        .expect_err("Expected the run to fail");
+    let records = client
+        .history(HistoryQuery {
+            logs: true,
+            ..by_hash(content)
+        })
+        .expect("Failed to query");
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record.failure, Some(error.to_string()));
+    assert_eq!(record.logs, error.logs());
+    assert!(record.samples.is_empty());
+    assert!(record.statistics().is_none());
+}
+
+#[test]
+fn time_range() {
+    let content = "// history: time\n1";
+    let client = client();
+    let start = now();
+    client.execute(content).expect("Failed to execute");
+    let within = client
+        .history(HistoryQuery {
+            since: Some(start),
+            until: Some(now() + 1),
+            ..by_hash(content)
+        })
+        .expect("Failed to query");
+    assert_eq!(within.len(), 1);
+    let later = client
+        .history(HistoryQuery {
+            since: Some(now() + 3600),
+            ..by_hash(content)
+        })
+        .expect("Failed to query");
+    assert!(later.is_empty());
+    // Without a hash every script within the range is returned.
+    let all = client
+        .history(HistoryQuery {
+            since: Some(start),
+            ..Default::default()
+        })
+        .expect("Failed to query");
+    assert!(all.iter().any(|record| record.id == within[0].id));
+}
+
+#[test]
+fn limit_keeps_newest() {
+    let content = "// history: limit\n1";
+    let client = client();
+    for _ in 0..3 {
+        client.execute(content).expect("Failed to execute");
+    }
+    let all = client.history(by_hash(content)).expect("Failed to query");
+    assert_eq!(all.len(), 3);
+    assert!(all.windows(2).all(|pair| pair[0].id < pair[1].id));
+    let newest = client
+        .history(HistoryQuery {
+            limit: Some(2),
+            ..by_hash(content)
+        })
+        .expect("Failed to query");
+    assert_eq!(newest, all[1..]);
+}
+
+#[test]
+fn without_history() {
+    common::start_server(&PLAIN_SERVER, PLAIN_PORT, Settings::default(), FakeArma);
+    let res = Client::connect_with_port("localhost", PLAIN_PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+        .history(HistoryQuery::default());
+    assert!(matches!(res, Err(Error::ServerError(_))));
+}
@@ -0,0 +1,112 @@
+use std::{
+    fs::{File, FileTimes},
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use arma_bench::ServerConfig;
+use arma_bench_server::{Installer, SteamCmd};
+
+/// A directory for servers, with a steamcmd that installs nothing, removed when dropped.
+struct Servers(PathBuf);
+
+impl Servers {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("arma-bench-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("Failed to create servers");
+        let steamcmd = dir.join("steamcmd.sh");
+        std::fs::write(&steamcmd, "#!/bin/sh\nexit 0\n").expect("Failed to write steamcmd");
+        std::fs::set_permissions(&steamcmd, std::fs::Permissions::from_mode(0o755))
+            .expect("Failed to make steamcmd executable");
+        Self(dir)
+    }
+
+    fn steamcmd(&self) -> SteamCmd {
+        SteamCmd::new(self.0.join("steamcmd.sh"), self.0.clone())
+    }
+
+    fn branch(&self, branch: &str) -> PathBuf {
+        let path = self.0.join(branch);
+        std::fs::create_dir_all(&path).expect("Failed to create branch");
+        path
+    }
+}
+
+impl Drop for Servers {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn config(branch: &str) -> ServerConfig {
+    ServerConfig {
+        branch: branch.to_string(),
+        ..ServerConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn unmarked_install() {
+    // Installed before the marker was written, it is used as it is.
+    let servers = Servers::new("unmarked");
+    let path = servers.branch("public");
+    let installed = servers
+        .steamcmd()
+        .install(&config("public"))
+        .await
+        .expect("Failed to install")
+        .path;
+    assert_eq!(installed, path);
+    assert!(!path.join(".tab-installed").exists());
+}
+
+#[tokio::test]
+async fn interrupted_install() {
+    std::env::set_var("STEAM_USER", "user");
+    std::env::set_var("STEAM_PASS", "pass");
+    let servers = Servers::new("interrupted");
+    let path = servers.branch("profiling");
+    std::fs::write(path.join(".tab-installing"), "").expect("Failed to write marker");
+    servers
+        .steamcmd()
+        .install(&config("profiling"))
+        .await
+        .expect("Failed to install");
+    assert!(path.join(".tab-installed").exists());
+    assert!(!path.join(".tab-installing").exists());
+}
+
+#[tokio::test]
+async fn not_updated_while_in_use() {
+    std::env::set_var("STEAM_USER", "user");
+    std::env::set_var("STEAM_PASS", "pass");
+    let servers = Servers::new("in-use");
+    let path = servers.branch("public");
+    std::fs::write(path.join(".tab-installed"), "").expect("Failed to write marker");
+    let steamcmd = std::sync::Arc::new(servers.steamcmd());
+    let running = steamcmd
+        .install(&config("public"))
+        .await
+        .expect("Failed to install");
+    // Goes stale while a server is running from it.
+    let stale = SystemTime::now() - Duration::from_hours(13);
+    File::options()
+        .write(true)
+        .open(path.join(".tab-installed"))
+        .and_then(|marker| marker.set_times(FileTimes::new().set_modified(stale)))
+        .expect("Failed to age marker");
+    let update = tokio::spawn({
+        let steamcmd = steamcmd.clone();
+        async move { steamcmd.install(&config("public")).await.map(|_| ()) }
+    });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!update.is_finished());
+    drop(running);
+    tokio::time::timeout(Duration::from_secs(5), update)
+        .await
+        .expect("Update did not start once the server exited")
+        .expect("Update panicked")
+        .expect("Failed to update");
+}
@@ -0,0 +1,83 @@
+use std::sync::Once;
+
+use arma_bench::{Client, CompareRequest, Error, Options, Request, ServerConfig};
+use arma_bench_server::Settings;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31856;
+
+fn start_server() {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+}
+
+fn client() -> Client {
+    start_server();
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+#[test]
+fn execute() {
+    let result = client()
+        .execute("// fake:log=first\n// fake:log=second")
+        .expect("Failed to execute");
+    // Only what was logged during the benchmark, not while Arma started.
+    assert_eq!(result.logs, ["first", "second"]);
+}
+
+#[test]
+fn compare() {
+    let results = client()
+        .compare(vec![
+            CompareRequest {
+                id: 0,
+                sqfc: false,
+                content: b"// fake:log=zero".to_vec(),
+            },
+            CompareRequest {
+                id: 1,
+                sqfc: false,
+                content: b"// fake:log=one".to_vec(),
+            },
+        ])
+        .expect("Failed to compare");
+    assert_eq!(results[0].logs, ["zero"]);
+    assert_eq!(results[1].logs, ["one"]);
+}
+
+#[test]
+fn failure() {
+    let res = client().execute("// fake:log=oops\n// fake:no-result");
+    let err = res.expect_err("Expected the run to fail");
+    assert!(matches!(err, Error::ResultMissing { .. }));
+    assert_eq!(err.logs(), ["oops"]);
+}
+
+#[test]
+fn never_started() {
+    let res = client().execute("// fake:never-start");
+    let err = res.expect_err("Expected the run to fail");
+    // Everything logged is shown when the benchmark never began.
+    assert_eq!(err.logs(), ["fake-arma starting"]);
+}
+
+#[test]
+fn skip_logs() {
+    let result = client()
+        .submit_with_options(
+            Request::Execute("// fake:log=hidden\n1".to_string()),
+            Options {
+                skip_logs: true,
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+        .into_execute()
+        .expect("Failed to execute");
+    assert!(result.logs.is_empty());
+}
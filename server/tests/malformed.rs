@@ -0,0 +1,174 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::Once,
+    time::Duration,
+};
+
+use arma_bench::{
+    Client, ClientHello, Message, Reply, Response, ServerConfig, ServerHello, HEADER_ID,
+};
+use arma_bench_server::{Arma, Settings, SteamCmd};
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31848;
+static MAX_MESSAGE_SIZE: u64 = 1024;
+
+fn start_server() {
+    let settings = Settings {
+        max_message_size: MAX_MESSAGE_SIZE,
+        ..Default::default()
+    };
+    common::start_server(&SERVER, PORT, settings, Arma::new(SteamCmd::default()));
+}
+
+/// Perform the handshake by hand, leaving the stream ready for requests.
+fn handshake() -> TcpStream {
+    let mut stream = TcpStream::connect(format!("localhost:{PORT}")).expect("Failed to connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    stream
+        .write_all(HEADER_ID)
+        .expect("Failed to send header ID");
+    let mut buf = [0; 16];
+    stream
+        .read_exact(&mut buf)
+        .expect("Failed to read header ID");
+    ClientHello::default()
+        .write(&mut stream)
+        .expect("Failed to send hello");
+    let hello = ServerHello::from_reader(&mut stream).expect("Failed to read hello");
+    assert!(matches!(hello, ServerHello::Accepted(_)));
+    ServerConfig::default()
+        .write(&mut stream)
+        .expect("Failed to send server config");
+    let mut buf = [0; 1];
+    stream.read_exact(&mut buf).expect("Failed to read ACK");
+    assert_eq!(buf[0], 1);
+    stream
+}
+
+/// The server should close the connection without sending anything else.
+fn assert_closed(stream: &mut TcpStream) {
+    let mut buf = [0; 1];
+    assert_eq!(stream.read(&mut buf).expect("Failed to read"), 0);
+}
+
+/// The server should still accept well-behaved clients.
+fn assert_alive() {
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect");
+}
+
+#[test]
+fn disconnect_during_handshake() {
+    start_server();
+    let mut stream = TcpStream::connect(format!("localhost:{PORT}")).expect("Failed to connect");
+    stream.write_all(&HEADER_ID[..8]).expect("Failed to send");
+    drop(stream);
+    assert_alive();
+}
+
+#[test]
+fn truncated_hello() {
+    start_server();
+    let mut stream = TcpStream::connect(format!("localhost:{PORT}")).expect("Failed to connect");
+    stream
+        .write_all(HEADER_ID)
+        .expect("Failed to send header ID");
+    let mut buf = [0; 16];
+    stream
+        .read_exact(&mut buf)
+        .expect("Failed to read header ID");
+    stream
+        .write_all(&32u64.to_le_bytes())
+        .expect("Failed to send length");
+    stream.write_all(&[0x93]).expect("Failed to send payload");
+    stream
+        .shutdown(std::net::Shutdown::Write)
+        .expect("Failed to shutdown");
+    assert_closed(&mut stream);
+    assert_alive();
+}
+
+#[test]
+fn truncated_request() {
+    start_server();
+    let mut stream = handshake();
+    stream
+        .write_all(&100u64.to_le_bytes())
+        .expect("Failed to send length");
+    stream.write_all(&[0; 10]).expect("Failed to send payload");
+    stream
+        .shutdown(std::net::Shutdown::Write)
+        .expect("Failed to shutdown");
+    assert_closed(&mut stream);
+    assert_alive();
+}
+
+#[test]
+fn oversized_length_prefix() {
+    start_server();
+    for len in [MAX_MESSAGE_SIZE + 1, u64::MAX] {
+        let mut stream = handshake();
+        stream
+            .write_all(&len.to_le_bytes())
+            .expect("Failed to send length");
+        // Rejected from the prefix alone, without waiting for a payload.
+        let reply = Reply::from_reader(&mut stream).expect("Failed to read response");
+        assert!(matches!(
+            reply,
+            Reply {
+                id: None,
+                response: Response::Error(_)
+            }
+        ));
+        assert_closed(&mut stream);
+    }
+    assert_alive();
+}
+
+#[test]
+fn request_at_limit() {
+    start_server();
+    let mut stream = handshake();
+    let payload = vec![0xc1; usize::try_from(MAX_MESSAGE_SIZE).expect("Limit fits")];
+    stream
+        .write_all(&MAX_MESSAGE_SIZE.to_le_bytes())
+        .expect("Failed to send length");
+    stream.write_all(&payload).expect("Failed to send payload");
+    // Accepted as a frame, rejected as msgpack, and the connection stays open.
+    let reply = Reply::from_reader(&mut stream).expect("Failed to read response");
+    assert!(matches!(
+        reply.response,
+        Response::Error(e) if e.starts_with("invalid request")
+    ));
+}
+
+#[test]
+fn garbage_msgpack() {
+    start_server();
+    let mut stream = handshake();
+    // 0xc1 is never used in msgpack, so this can not decode as anything.
+    for _ in 0..2 {
+        stream
+            .write_all(&4u64.to_le_bytes())
+            .expect("Failed to send length");
+        stream
+            .write_all(&[0xc1; 4])
+            .expect("Failed to send payload");
+        let reply = Reply::from_reader(&mut stream).expect("Failed to read response");
+        assert!(matches!(
+            reply,
+            Reply {
+                id: None,
+                response: Response::Error(_)
+            }
+        ));
+    }
+    drop(stream);
+    assert_alive();
+}
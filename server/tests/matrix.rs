@@ -0,0 +1,99 @@
+use std::sync::Once;
+
+use arma_bench::{Client, Error, MatrixRequest, Options, Parameters, Request, ServerConfig};
+use arma_bench_server::Settings;
+use arma_rs::Value;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31862;
+
+fn client() -> Client {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+fn sizes(sizes: &[f64]) -> Vec<Parameters> {
+    sizes
+        .iter()
+        .map(|size| Parameters::from([("_size".to_string(), Value::Number(*size))]))
+        .collect()
+}
+
+#[test]
+fn results_in_order() {
+    let results = client()
+        .matrix("_size", sizes(&[10.0, 100.0, 1000.0]))
+        .expect("Failed to run matrix");
+    assert_eq!(results.len(), 3);
+    for (result, size) in results.iter().zip([10.0, 100.0, 1000.0]) {
+        assert_eq!(result.parameters["_size"], Value::Number(size));
+        assert_eq!(result.result.ret, Value::Number(size));
+    }
+}
+
+#[test]
+fn logs_per_set() {
+    let parameters = ["small", "large"]
+        .into_iter()
+        .map(|name| Parameters::from([("_name".to_string(), Value::String(name.to_string()))]))
+        .collect();
+    let results = client()
+        .matrix("// fake:log=_name", parameters)
+        .expect("Failed to run matrix");
+    assert_eq!(results[0].result.logs, ["\"small\""]);
+    assert_eq!(results[1].result.logs, ["\"large\""]);
+}
+
+#[test]
+fn repetitions() {
+    let results = client()
+        .submit_with_options(
+            Request::Matrix(MatrixRequest {
+                template: "_size".to_string(),
+                parameters: sizes(&[1.0, 2.0]),
+            }),
+            Options {
+                repetitions: Some(4),
+                boots: Some(2),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+        .into_matrix()
+        .expect("Failed to run matrix");
+    for result in results {
+        assert_eq!(result.result.samples.len(), 8);
+    }
+}
+
+#[test]
+fn invalid_names() {
+    for name in ["size", "_", "_a b", "_code", "_Samples"] {
+        let res = client().matrix(
+            "1",
+            vec![Parameters::from([(name.to_string(), Value::Number(1.0))])],
+        );
+        assert!(
+            matches!(&res, Err(Error::ServerError(_))),
+            "{name} was accepted: {res:?}"
+        );
+    }
+}
+
+#[test]
+fn empty() {
+    let res = client().matrix("1", Vec::new());
+    assert!(matches!(res, Err(Error::ServerError(e)) if e.contains("sets of parameters")));
+}
+
+#[test]
+fn non_finite() {
+    let res = client().matrix("1", sizes(&[f64::NAN]));
+    assert!(matches!(res, Err(Error::ServerError(e)) if e.contains("_size")));
+}
@@ -0,0 +1,101 @@
+use std::sync::Once;
+
+use arma_bench::{AsyncClient, Client, Request, ServerConfig};
+use arma_bench_server::Settings;
+use arma_rs::Value;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31853;
+
+fn start_server() {
+    let settings = Settings {
+        workers: 2,
+        ..Default::default()
+    };
+    common::start_server(&SERVER, PORT, settings, FakeArma);
+}
+
+#[test]
+fn out_of_order() {
+    start_server();
+    let client = Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect");
+    let slow = client
+        .submit(Request::Execute("// fake:sleep=1000\n1".to_string()))
+        .expect("Failed to submit");
+    let fast = client
+        .submit(Request::Execute("2".to_string()))
+        .expect("Failed to submit");
+    assert_ne!(slow.id(), fast.id());
+
+    // Both are running on their own worker, the second finishes first.
+    let fast = fast
+        .wait()
+        .expect("Failed to wait")
+        .into_execute()
+        .expect("Failed to execute");
+    assert_eq!(fast.ret, Value::Number(2.0));
+    let slow = slow
+        .wait()
+        .expect("Failed to wait")
+        .into_execute()
+        .expect("Failed to execute");
+    assert_eq!(slow.ret, Value::String("// fake:sleep=1000\n1".to_string()));
+}
+
+#[test]
+fn many_queued() {
+    start_server();
+    let client = Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect");
+    let pending = (0..5u32)
+        .map(|i| {
+            client
+                .submit(Request::Execute(i.to_string()))
+                .expect("Failed to submit")
+        })
+        .collect::<Vec<_>>();
+    for (i, pending) in (0..5u32).zip(pending).rev() {
+        let result = pending
+            .wait()
+            .expect("Failed to wait")
+            .into_execute()
+            .expect("Failed to execute");
+        assert_eq!(result.ret, Value::Number(f64::from(i)));
+    }
+}
+
+#[test]
+fn async_pipeline() {
+    start_server();
+    tokio::runtime::Runtime::new()
+        .expect("Failed to create runtime")
+        .block_on(async {
+            let client =
+                AsyncClient::connect_with_port("localhost", PORT, &ServerConfig::default())
+                    .await
+                    .expect("Failed to connect");
+            let first = client
+                .submit(Request::Execute("1".to_string()))
+                .await
+                .expect("Failed to submit");
+            let second = client
+                .submit(Request::Execute("2".to_string()))
+                .await
+                .expect("Failed to submit");
+            let (first, second) = tokio::join!(first.wait(), second.wait());
+            let first = first
+                .expect("Failed to wait")
+                .into_execute()
+                .expect("Failed to execute");
+            let second = second
+                .expect("Failed to wait")
+                .into_execute()
+                .expect("Failed to execute");
+            assert_eq!(first.ret, Value::Number(1.0));
+            assert_eq!(second.ret, Value::Number(2.0));
+        });
+}
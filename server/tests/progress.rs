@@ -0,0 +1,48 @@
+use std::{sync::Once, time::Duration};
+
+use arma_bench::{Client, Progress, ServerConfig};
+use arma_bench_server::Settings;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31851;
+
+fn start_server() {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+}
+
+fn client() -> Client {
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+#[test]
+fn queue_position() {
+    start_server();
+    // One request running and one waiting, so the third starts at position 1.
+    let running = std::thread::spawn(|| client().execute("// fake:sleep=1000"));
+    std::thread::sleep(Duration::from_millis(200));
+    let waiting = std::thread::spawn(|| client().execute("// fake:sleep=200"));
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut positions = Vec::new();
+    client()
+        .execute_with_progress("1", |p| {
+            if let Progress::Queued { position } = p {
+                positions.push(position);
+            }
+        })
+        .expect("Failed to execute");
+    assert_eq!(positions, [1, 0]);
+
+    running
+        .join()
+        .expect("Client thread panicked")
+        .expect("Failed to execute");
+    waiting
+        .join()
+        .expect("Client thread panicked")
+        .expect("Failed to execute");
+}
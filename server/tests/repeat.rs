@@ -0,0 +1,116 @@
+use std::sync::Once;
+
+use arma_bench::{Client, CompareRequest, Options, Request, Response, ServerConfig};
+use arma_bench_server::Settings;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31858;
+
+fn start_server() {
+    let settings = Settings {
+        max_boots: 3,
+        ..Default::default()
+    };
+    common::start_server(&SERVER, PORT, settings, FakeArma);
+}
+
+fn submit(request: Request, options: Options) -> Response {
+    start_server();
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+        .submit_with_options(request, options)
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+}
+
+#[test]
+fn repetitions() {
+    let result = submit(
+        Request::Execute("1".to_string()),
+        Options {
+            repetitions: Some(5),
+            ..Default::default()
+        },
+    )
+    .into_execute()
+    .expect("Failed to execute");
+    assert_eq!(result.samples.len(), 5);
+    assert!((result.time - result.samples[0]).abs() < f64::EPSILON);
+    let stats = result.statistics();
+    assert!(stats.min < stats.max);
+    assert!((stats.median - result.samples[2]).abs() < f64::EPSILON);
+}
+
+#[test]
+fn boots() {
+    let results = submit(
+        Request::Compare(vec![
+            CompareRequest {
+                id: 0,
+                sqfc: false,
+                content: b"0".to_vec(),
+            },
+            CompareRequest {
+                id: 1,
+                sqfc: false,
+                content: b"1".to_vec(),
+            },
+        ]),
+        Options {
+            repetitions: Some(2),
+            boots: Some(3),
+            ..Default::default()
+        },
+    )
+    .into_compare()
+    .expect("Failed to compare");
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert_eq!(result.samples.len(), 6);
+    }
+}
+
+#[test]
+fn single() {
+    let result = submit(Request::Execute("1".to_string()), Options::default())
+        .into_execute()
+        .expect("Failed to execute");
+    assert_eq!(result.samples.len(), 1);
+    assert!((result.statistics().stddev).abs() < f64::EPSILON);
+}
+
+#[test]
+fn failed_boot() {
+    let res = submit(
+        Request::Execute("// fake:no-result".to_string()),
+        Options {
+            boots: Some(2),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(res, Response::ResultMissing { .. }));
+}
+
+#[test]
+fn invalid() {
+    let res = submit(
+        Request::Execute("1".to_string()),
+        Options {
+            repetitions: Some(0),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(res, Response::Error(e) if e == "repetitions must be at least 1"));
+    let res = submit(
+        Request::Execute("1".to_string()),
+        Options {
+            boots: Some(4),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(res, Response::Error(e) if e == "boots must be between 1 and 3"));
+}
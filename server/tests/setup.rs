@@ -0,0 +1,76 @@
+use std::sync::Once;
+
+use arma_bench::{Client, CompareRequest, Error, Options, Request, Response, ServerConfig};
+use arma_bench_server::Settings;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31860;
+
+fn options() -> Options {
+    Options {
+        setup: Some("// fake:log=setup".to_string()),
+        teardown: Some("// fake:log=teardown".to_string()),
+        ..Default::default()
+    }
+}
+
+fn submit(request: Request, options: Options) -> Response {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+        .submit_with_options(request, options)
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+}
+
+#[test]
+fn execute() {
+    let result = submit(
+        Request::Execute("// fake:log=bench\n1".to_string()),
+        options(),
+    )
+    .into_execute()
+    .expect("Failed to execute");
+    assert_eq!(result.logs, ["setup", "bench", "teardown"]);
+}
+
+#[test]
+fn compare() {
+    let results = submit(
+        Request::Compare(vec![
+            CompareRequest {
+                id: 0,
+                sqfc: false,
+                content: b"// fake:log=zero".to_vec(),
+            },
+            CompareRequest {
+                id: 1,
+                sqfc: false,
+                content: b"// fake:log=one".to_vec(),
+            },
+        ]),
+        options(),
+    )
+    .into_compare()
+    .expect("Failed to compare");
+    // Run around every script, not once for the request.
+    assert_eq!(results[0].logs, ["setup", "zero", "teardown"]);
+    assert_eq!(results[1].logs, ["setup", "one", "teardown"]);
+}
+
+#[test]
+fn setup_compile_error() {
+    let res = submit(
+        Request::Execute("1".to_string()),
+        Options {
+            setup: Some("// fake:compile-error".to_string()),
+            ..Default::default()
+        },
+    )
+    .into_execute();
+    assert!(matches!(res, Err(Error::CompileFailed { file, .. }) if file == "setup.sqf"));
+}
@@ -0,0 +1,258 @@
+use std::{path::Path, sync::Once};
+
+use arma_bench::{Error, Options, ServerConfig, Suite};
+use arma_bench_server::Settings;
+use arma_rs::Value;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31866;
+
+fn run(manifest: &str) -> Result<arma_bench::Report, Error> {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+    Suite::parse(manifest, Path::new("."))
+        .expect("Failed to parse suite")
+        .run(
+            "localhost",
+            PORT,
+            &ServerConfig::default(),
+            &Options::default(),
+        )
+}
+
+#[test]
+fn groups_per_parameter_set() {
+    let report = run(r#"
+repetitions = 2
+
+[[benchmark]]
+name = "count"
+parameters = [{ _size = 10 }, { _size = 100 }]
+
+[[benchmark.variant]]
+name = "fast"
+code = "1"
+
+[[benchmark.variant]]
+name = "slow"
+code = "1"
+
+[[benchmark]]
+name = "other"
+branch = "profiling"
+
+[[benchmark.variant]]
+name = "only"
+code = "3"
+"#)
+    .expect("Failed to run suite");
+    let names = report
+        .groups
+        .iter()
+        .map(|group| group.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["count (_size = 10)", "count (_size = 100)", "other"]
+    );
+    assert_eq!(report.groups[0].entries[0].name, "fast");
+    assert_eq!(report.groups[0].entries[1].name, "slow");
+    assert_eq!(report.groups[0].entries[0].statistics.samples.len(), 2);
+    assert_eq!(report.groups[2].entries[0].result, "3");
+    assert!(!report.failed());
+}
+
+#[test]
+fn expected_results() {
+    let report = run(r#"
+[[benchmark]]
+name = "sum"
+expect = 3
+
+[[benchmark.variant]]
+name = "right"
+code = "3"
+
+[[benchmark.variant]]
+name = "wrong"
+code = "4"
+"#)
+    .expect("Failed to run suite");
+    let entries = &report.groups[0].entries;
+    assert!(entries[0].failures.is_empty(), "{:?}", entries[0].failures);
+    assert_eq!(entries[1].failures.len(), 1);
+    assert!(report.failed());
+}
+
+#[test]
+fn assert_equal_to_baseline() {
+    let report = run(r#"
+[[benchmark]]
+name = "sum"
+baseline = "b"
+assert_equal = true
+
+[[benchmark.variant]]
+name = "a"
+code = "4"
+
+[[benchmark.variant]]
+name = "b"
+code = "3"
+"#)
+    .expect("Failed to run suite");
+    let a = report.groups[0]
+        .entries
+        .iter()
+        .find(|entry| entry.name == "a")
+        .expect("Missing a");
+    assert_eq!(a.failures.len(), 1);
+}
+
+#[test]
+fn thresholds() {
+    let report = run(r#"
+[thresholds]
+max_mean = 0.0015
+
+[[benchmark]]
+name = "sum"
+
+[[benchmark.variant]]
+name = "a"
+code = "1"
+
+[[benchmark.variant]]
+name = "b"
+code = "1"
+"#)
+    .expect("Failed to run suite");
+    let entries = &report.groups[0].entries;
+    assert!(entries[0].failures.is_empty());
+    assert!(entries[1].failures[0].contains("above the limit"));
+}
+
+#[test]
+fn failed_benchmark() {
+    let report = run(r#"
+[[benchmark]]
+name = "broken"
+parameters = [{ _size = 1 }, { _size = 2 }]
+
+[[benchmark.variant]]
+name = "a"
+code = "// fake:compile-error"
+
+[[benchmark]]
+name = "working"
+
+[[benchmark.variant]]
+name = "b"
+code = "1"
+"#)
+    .expect("Failed to run suite");
+    // The rest of the suite still runs, and the failure is in the report.
+    let names = report
+        .groups
+        .iter()
+        .map(|group| group.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["broken (_size = 1)", "broken (_size = 2)", "working"]
+    );
+    assert!(report.groups[0].entries.is_empty());
+    assert!(
+        report.groups[0].failures[0].contains("failed to compile"),
+        "{:?}",
+        report.groups[0].failures
+    );
+    assert!(report.groups[2].failures.is_empty());
+    assert_eq!(report.groups[2].entries[0].result, "1");
+    assert_eq!(report.failures(), 2);
+}
+
+#[test]
+fn assert_equal_with_parameters() {
+    let report = run(r#"
+[[benchmark]]
+name = "size"
+parameters = [{ _size = 1 }, { _size = 10 }]
+assert_equal = true
+
+[[benchmark.variant]]
+name = "size"
+code = "_size"
+
+[[benchmark.variant]]
+name = "one"
+code = "1"
+"#)
+    .expect("Failed to run suite");
+    let one = |group: usize| {
+        report.groups[group]
+            .entries
+            .iter()
+            .find(|entry| entry.name == "one")
+            .expect("Missing one")
+    };
+    // Checked by the client, the server only asserts comparisons.
+    assert!(one(0).failures.is_empty(), "{:?}", one(0).failures);
+    assert_eq!(one(1).failures.len(), 1);
+}
+
+#[test]
+fn unchecked_parameters() {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+    let mut suite = Suite::parse(
+        r#"
+[[benchmark]]
+name = "name"
+
+[[benchmark.variant]]
+name = "a"
+code = "1"
+
+[[benchmark]]
+name = "value"
+
+[[benchmark.variant]]
+name = "a"
+code = "1"
+
+[[benchmark]]
+name = "working"
+
+[[benchmark.variant]]
+name = "b"
+code = "1"
+"#,
+        Path::new("."),
+    )
+    .expect("Failed to parse suite");
+    // Built without the manifest, so nothing checked them.
+    suite.benchmarks[0].parameters = vec![[("_a = 1; x".to_string(), Value::Number(1.0))].into()];
+    suite.benchmarks[1].parameters = vec![[("_a".to_string(), Value::Number(f64::NAN))].into()];
+    let report = suite
+        .run(
+            "localhost",
+            PORT,
+            &ServerConfig::default(),
+            &Options::default(),
+        )
+        .expect("Failed to run suite");
+    assert!(
+        report.groups[0].failures[0].contains("invalid parameter name"),
+        "{:?}",
+        report.groups[0].failures
+    );
+    assert!(
+        report.groups[1].failures[0].contains("not a finite number"),
+        "{:?}",
+        report.groups[1].failures
+    );
+    assert_eq!(report.groups[2].entries[0].result, "1");
+    assert_eq!(report.failures(), 2);
+}
@@ -0,0 +1,108 @@
+use std::{
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+use arma_bench::{AsyncClient, Client, Error, Options, Request, ServerConfig};
+use arma_bench_server::Settings;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31854;
+
+fn start_server() {
+    let settings = Settings {
+        max_timeout: 60,
+        watchdog_grace: Duration::from_millis(200),
+        ..Default::default()
+    };
+    common::start_server(&SERVER, PORT, settings, FakeArma);
+}
+
+fn execute(content: &str, timeout: Option<u64>) -> Result<arma_bench::ExecuteResult, Error> {
+    start_server();
+    let client = Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect");
+    client
+        .submit_with_options(
+            Request::Execute(content.to_string()),
+            Options {
+                timeout,
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+        .into_execute()
+}
+
+#[test]
+fn requested_timeout() {
+    let res = execute("// fake:timeout", Some(5));
+    assert!(matches!(res, Err(Error::RunTimedOut { seconds: 5, .. })));
+}
+
+#[test]
+fn default_timeout() {
+    let res = execute("// fake:timeout", None);
+    assert!(matches!(res, Err(Error::RunTimedOut { seconds: 30, .. })));
+}
+
+#[test]
+fn over_maximum() {
+    for timeout in [0, 61] {
+        let res = execute("1", Some(timeout));
+        assert!(
+            matches!(res, Err(Error::ServerError(e)) if e == "timeout must be between 1 and 60 seconds")
+        );
+    }
+}
+
+#[test]
+fn watchdog() {
+    let start = Instant::now();
+    let res = execute("// fake:hang", Some(1));
+    assert!(matches!(res, Err(Error::RunTimedOut { seconds: 1, .. })));
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[test]
+fn client_timeout() {
+    start_server();
+    let client = Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect");
+    client
+        .set_timeout(Some(Duration::from_millis(200)))
+        .expect("Failed to set timeout");
+    let pending = client
+        .submit(Request::Execute("// fake:sleep=2000".to_string()))
+        .expect("Failed to submit");
+    let id = pending.id();
+    assert!(matches!(pending.wait(), Err(Error::Timeout)));
+    // No longer waited on, and the client still takes requests.
+    assert!(!client.cancel(id).expect("Failed to cancel"));
+    client.set_timeout(None).expect("Failed to clear timeout");
+    let result = client.execute("1").expect("Failed to execute");
+    assert_eq!(result.ret, arma_rs::Value::Number(1.0));
+}
+
+#[tokio::test]
+async fn async_client_timeout() {
+    start_server();
+    let client = AsyncClient::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .await
+        .expect("Failed to connect");
+    let pending = client
+        .submit(Request::Execute("// fake:sleep=2000".to_string()))
+        .await
+        .expect("Failed to submit");
+    let id = pending.id();
+    let res = tokio::time::timeout(Duration::from_millis(200), pending.wait()).await;
+    assert!(res.is_err());
+    assert!(!client.cancel(id).await.expect("Failed to cancel"));
+    let result = client.execute("1").await.expect("Failed to execute");
+    assert_eq!(result.ret, arma_rs::Value::Number(1.0));
+}
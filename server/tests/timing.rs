@@ -0,0 +1,121 @@
+use std::sync::Once;
+
+use arma_bench::{
+    Client, CompareRequest, Error, ExecuteResult, Options, Request, ServerConfig, Timing,
+};
+use arma_bench_server::Settings;
+use arma_rs::Value;
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31864;
+
+fn client() -> Client {
+    common::start_server(&SERVER, PORT, Settings::default(), FakeArma);
+    Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+        .expect("Failed to connect")
+}
+
+fn execute(content: &str, options: Options) -> Result<ExecuteResult, Error> {
+    client()
+        .submit_with_options(Request::Execute(content.to_string()), options)?
+        .wait()?
+        .into_execute()
+}
+
+#[test]
+fn defaults() {
+    let result = execute("1", Options::default()).expect("Failed to execute");
+    assert_eq!(result.timing, Timing::CodePerformance);
+    assert_eq!(result.iter, 10000);
+}
+
+#[test]
+fn cycles() {
+    for timing in [Timing::CodePerformance, Timing::TickTime] {
+        let result = execute(
+            "1",
+            Options {
+                cycles: Some(500),
+                timing: Some(timing),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to execute");
+        assert_eq!(result.timing, timing);
+        assert_eq!(result.iter, 500);
+    }
+}
+
+#[test]
+fn arguments() {
+    for timing in [Timing::CodePerformance, Timing::TickTime] {
+        let result = execute(
+            "_this",
+            Options {
+                arguments: Some(Value::Number(5.0)),
+                timing: Some(timing),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to execute");
+        assert_eq!(result.ret, Value::Number(5.0));
+    }
+}
+
+#[test]
+fn compare_reports_timing() {
+    let results = client()
+        .submit_with_options(
+            Request::Compare(vec![
+                CompareRequest {
+                    id: 0,
+                    sqfc: false,
+                    content: b"0".to_vec(),
+                },
+                CompareRequest {
+                    id: 1,
+                    sqfc: false,
+                    content: b"1".to_vec(),
+                },
+            ]),
+            Options {
+                timing: Some(Timing::TickTime),
+                ..Default::default()
+            },
+        )
+        .expect("Failed to submit")
+        .wait()
+        .expect("Failed to wait")
+        .into_compare()
+        .expect("Failed to compare");
+    assert!(results
+        .iter()
+        .all(|result| result.timing == Timing::TickTime));
+}
+
+#[test]
+fn zero_cycles() {
+    let res = execute(
+        "1",
+        Options {
+            cycles: Some(0),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(res, Err(Error::ServerError(e)) if e == "cycles must be at least 1"));
+}
+
+#[test]
+fn non_finite_arguments() {
+    let res = execute(
+        "1",
+        Options {
+            arguments: Some(Value::Array(vec![Value::Number(f64::INFINITY)])),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(res, Err(Error::ServerError(e)) if e.contains("finite")));
+}
@@ -0,0 +1,56 @@
+use std::{
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+use arma_bench::{Client, ServerConfig};
+use arma_bench_server::{Settings, Worker};
+use common::FakeArma;
+
+mod common;
+
+static SERVER: Once = Once::new();
+static PORT: u16 = 31850;
+
+fn start_server() {
+    let settings = Settings {
+        workers: 2,
+        ..Default::default()
+    };
+    common::start_server(&SERVER, PORT, settings, FakeArma);
+}
+
+#[test]
+fn parallel() {
+    start_server();
+    let start = Instant::now();
+    let handles = (0..2)
+        .map(|_| {
+            std::thread::spawn(|| {
+                Client::connect_with_port("localhost", PORT, &ServerConfig::default())
+                    .expect("Failed to connect")
+                    .execute("// fake:sleep=1000")
+                    .expect("Failed to execute")
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().expect("Client thread panicked");
+    }
+    // Run one after the other this would take at least two seconds.
+    assert!(start.elapsed() < Duration::from_millis(1900));
+}
+
+#[test]
+fn worker_layout() {
+    let settings = Settings {
+        workers: 2,
+        cpus_per_worker: Some(2),
+        ..Default::default()
+    };
+    let first = Worker::new(0, &settings);
+    let second = Worker::new(1, &settings);
+    assert_eq!(first.cpus, Some(vec![0, 1]));
+    assert_eq!(second.cpus, Some(vec![2, 3]));
+    assert_ne!(first.profiles(), second.profiles());
+}